@@ -0,0 +1,142 @@
+//! Remembering which files were clean, so later runs can skip them.
+//!
+//! Files are identified by their path, size and modification time. The whole
+//! cache is discarded when the settings change, which the caller detects by
+//! passing a hash of everything which affects the results.
+//!
+//! The cache is a text file, with the settings hash on the first line and
+//! then a line for each file, with its size, modification time in nanoseconds
+//! and path, separated by spaces.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io;
+use std::time::UNIX_EPOCH;
+
+use crate::error::Error;
+
+pub const DEFAULT_LOCATION: & str = ".fix-whitespace-cache";
+
+pub struct Cache {
+	path: String,
+	settings_hash: String,
+	files: HashMap <String, (u64, u128)>,
+}
+
+impl Cache {
+
+	/// Load the cache, or start an empty one if it doesn't exist or was
+	/// written with other settings.
+	pub fn load (
+		path: & str,
+		settings: & str,
+	) -> Result <Cache, Error> {
+
+		let mut cache = Cache {
+			path: path.to_owned (),
+			settings_hash: hash (settings),
+			files: HashMap::new (),
+		};
+
+		let contents = match fs::read_to_string (path) {
+			Ok (contents) => contents,
+			Err (error) if error.kind () == io::ErrorKind::NotFound => return Ok (cache),
+			Err (error) => return Err (Error::io ("reading", path, error)),
+		};
+
+		let mut lines = contents.lines ();
+
+		if lines.next () != Some (cache.settings_hash.as_str ()) {
+			return Ok (cache);
+		}
+
+		for line in lines {
+			let mut parts = line.splitn (3, ' ');
+			if let (Some (size), Some (modified), Some (filename)) =
+					(parts.next (), parts.next (), parts.next ()) {
+				if let (Ok (size), Ok (modified)) = (size.parse (), modified.parse ()) {
+					cache.files.insert (filename.to_owned (), (size, modified));
+				}
+			}
+		}
+
+		Ok (cache)
+
+	}
+
+	/// Whether the file was clean last time, and hasn't changed since.
+	pub fn is_clean (
+		& self,
+		filename: & str,
+	) -> bool {
+
+		match (self.files.get (filename), file_key (filename)) {
+			(Some (cached), Some (current)) => * cached == current,
+			_ => false,
+		}
+
+	}
+
+	/// Remember that the file is clean as it is now, or forget it if it can't
+	/// be read.
+	pub fn set_clean (
+		& mut self,
+		filename: & str,
+		clean: bool,
+	) {
+
+		match file_key (filename) {
+			Some (key) if clean && ! filename.contains ('\n') =>
+				self.files.insert (filename.to_owned (), key),
+			_ => self.files.remove (filename),
+		};
+
+	}
+
+	pub fn save (
+		& self,
+	) -> Result <(), Error> {
+
+		let mut files: Vec <_> = self.files.iter ().collect ();
+		files.sort ();
+
+		let mut contents = format! ("{}\n", self.settings_hash);
+
+		for (filename, (size, modified)) in files {
+			contents.push_str (& format! ("{} {} {}\n", size, modified, filename));
+		}
+
+		fs::write (& self.path, contents)
+			.map_err (|error| Error::io ("writing", & self.path, error))
+
+	}
+
+}
+
+fn file_key (
+	filename: & str,
+) -> Option <(u64, u128)> {
+
+	let metadata = fs::metadata (filename).ok () ?;
+	let modified = metadata.modified ().ok () ?.duration_since (UNIX_EPOCH).ok () ?;
+
+	Some ((metadata.len (), modified.as_nanos ()))
+
+}
+
+fn hash (
+	settings: & str,
+) -> String {
+
+	let mut hasher = DefaultHasher::new ();
+	env! ("CARGO_PKG_VERSION").hash (& mut hasher);
+	settings.hash (& mut hasher);
+
+	format! ("{:016x}", hasher.finish ())
+
+}
+
+// ex: noet ts=4 filetype=rust
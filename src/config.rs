@@ -0,0 +1,885 @@
+//! Settings which control which problems are detected and how they are
+//! fixed.
+
+use regex::Regex;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::config_file::ConfigFile;
+use crate::config_file::Setting;
+use crate::config_file::Value;
+use crate::diff::LineRanges;
+use crate::filetype;
+#[ cfg (feature = "fs") ]
+use crate::gitattributes;
+use crate::indent;
+use crate::log;
+use crate::modeline;
+use crate::width::TabStops;
+
+#[ derive (Clone, Debug) ]
+#[ cfg_attr (feature = "cli", derive (clap::Args)) ]
+pub struct Config {
+
+	#[ cfg_attr (feature = "cli", clap (long)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_EXPAND_TABS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Expand tabs into spaces")) ]
+	pub expand_tabs: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long, arg_enum, default_value = "configured")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_INDENT")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Use the configured indent style or detect it from each file")) ]
+	pub indent: Indent,
+
+	#[ cfg_attr (feature = "cli", clap (long)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_SMART_TABS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Indent with tabs but align with spaces")) ]
+	pub smart_tabs: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long, default_value = "4")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_TAB_SIZE")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Tab size when expanding or calculating line length")) ]
+	pub tab_size: usize,
+
+	#[ cfg_attr (feature = "cli", clap (long, value_name = "COLUMNS", use_value_delimiter = true)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_TAB_STOPS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Distances between tab stops, if they vary, eg 8,4 like vim's vartabstop")) ]
+	pub tab_stops: Vec <usize>,
+
+	#[ cfg_attr (feature = "cli", clap (long, value_name = "COLUMNS")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_INDENT_SIZE")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Width of each level of indentation, defaulting to the tab size")) ]
+	pub indent_size: Option <usize>,
+
+	#[ cfg_attr (feature = "cli", clap (long)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_NORMALISE_INDENT")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Round indentation to a multiple of the indent size, eg 3 spaces to 4")) ]
+	pub normalise_indent: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long, env = "FIX_WHITESPACE_LINE_LENGTH", default_value = "80")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Maximum line length")) ]
+	pub line_length: usize,
+
+	#[ cfg_attr (feature = "cli", clap (flatten)) ]
+	pub line_length_options: LineLengthOptions,
+
+	#[ cfg_attr (feature = "cli", clap (long, arg_enum, default_value = "lf")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_LINE_ENDING")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Line ending to normalise to")) ]
+	pub line_ending: LineEnding,
+
+	#[ cfg_attr (feature = "cli", clap (long)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_FINAL_NEWLINE")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Add a line ending to the last line if it doesn't have one")) ]
+	pub final_newline: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long, arg_enum, default_value = "report")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_UNICODE_WHITESPACE")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Report, replace or remove unicode whitespace characters")) ]
+	pub unicode_whitespace: UnicodeWhitespace,
+
+	#[ cfg_attr (feature = "cli", clap (long, arg_enum, default_value = "report")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_CONTROL_CHARACTERS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Report, strip or replace control characters")) ]
+	pub control_characters: ControlCharacters,
+
+	#[ cfg_attr (feature = "cli", clap (long, arg_enum, default_value = "report")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_FORM_FEEDS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Report or remove form feeds, or replace them with blank lines")) ]
+	pub form_feeds: FormFeeds,
+
+	#[ cfg_attr (feature = "cli", clap (long, arg_enum, default_value = "report")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_VERTICAL_TABS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Report or remove vertical tabs")) ]
+	pub vertical_tabs: VerticalTabs,
+
+	#[ cfg_attr (feature = "cli", clap (long, arg_enum, default_value = "preserve")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_MARKDOWN_LINE_BREAKS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Preserve, convert to backslash or remove two space line breaks in markdown")) ]
+	pub markdown_line_breaks: MarkdownLineBreaks,
+
+	#[ cfg_attr (feature = "cli", clap (long)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_MAX_BLANK_LINES")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Maximum number of consecutive blank lines")) ]
+	pub max_blank_lines: Option <usize>,
+
+	#[ cfg_attr (feature = "cli", clap (long)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_INCLUDE_GENERATED")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Process files marked as generated, eg with @generated or DO NOT EDIT")) ]
+	pub include_generated: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long, value_name = "RULE", multiple_occurrences = true, use_value_delimiter = true)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_ENABLE")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Only run these rules, eg trailing-whitespace")) ]
+	pub enable: Vec <String>,
+
+	#[ cfg_attr (feature = "cli", clap (long, value_name = "RULE", multiple_occurrences = true, use_value_delimiter = true)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_DISABLE")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Don't run these rules, eg line-length")) ]
+	pub disable: Vec <String>,
+
+	#[ cfg_attr (feature = "cli", clap (long, value_name = "RULE=LEVEL", multiple_occurrences = true, use_value_delimiter = true)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_SEVERITY")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Set rules to error, warning or off, eg line-length=warning")) ]
+	pub severity: Vec <RuleSeverity>,
+
+	#[ cfg_attr (feature = "cli", clap (long, value_name = "REGEX", multiple_occurrences = true)) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_IGNORE_LINE_PATTERN")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Leave lines matching this regex alone, eg long #define tables")) ]
+	pub ignore_line_pattern: Vec <Regex>,
+
+	#[ cfg_attr (feature = "cli", clap (skip)) ]
+	pub filetype: Option <String>,
+
+	/// Only check and fix these lines, eg those changed in a diff.
+	#[ cfg_attr (feature = "cli", clap (skip)) ]
+	pub line_ranges: Option <LineRanges>,
+
+}
+
+impl Config {
+
+	/// Resolve the settings for a particular file, from its file type,
+	/// sections in the config file for its type, name and path, git
+	/// attributes, its indentation and its modeline.
+	pub fn for_file (
+		& self,
+		config_file: Option <& ConfigFile>,
+		filename: & str,
+		contents: & str,
+	) -> Config {
+
+		let mut config = self.clone ();
+
+		let modeline = modeline::find (contents);
+
+		let filetype = modeline.as_deref ()
+			.and_then (modeline::filetype)
+			.or_else (|| filetype::detect (filename, contents));
+
+		if let Some (filetype) = filetype {
+			log::debug (format_args! (
+				"file={:?} filetype={} source={}",
+				filename,
+				filetype,
+				if modeline.as_deref ().and_then (modeline::filetype).is_some () {
+					"modeline"
+				} else {
+					"detected"
+				}));
+			config.filetype = Some (filetype.to_owned ());
+		}
+
+		if let Some (config_file) = config_file {
+			config.apply_config_file (config_file, filename);
+		}
+
+		#[ cfg (feature = "fs") ]
+		if let Some (line_ending) = gitattributes::line_ending (filename) {
+			log::debug (format_args! (
+				"file={:?} line-ending={:?} source=gitattributes",
+				filename,
+				line_ending));
+			config.line_ending = line_ending;
+		}
+
+		if config.indent == Indent::Auto {
+			if let Some (indent_style) = indent::detect (contents) {
+				log::debug (format_args! (
+					"file={:?} expand-tabs={} tab-size={} source=detected",
+					filename,
+					! indent_style.tabs,
+					indent_style.size.map_or ("unchanged".to_owned (), |size| size.to_string ())));
+				config.expand_tabs = ! indent_style.tabs;
+				if let Some (size) = indent_style.size {
+					config.tab_size = size;
+					config.indent_size = Some (size);
+				}
+			}
+		}
+
+		if let Some (modeline) = modeline {
+			log::debug (format_args! ("file={:?} modeline={:?}", filename, modeline));
+			modeline::apply (& mut config, & modeline);
+		}
+
+		log::trace (format_args! ("file={:?} config={:?}", filename, config));
+
+		config
+
+	}
+
+	fn apply_config_file (
+		& mut self,
+		config_file: & ConfigFile,
+		filename: & str,
+	) {
+
+		// settings are checked when the config file is loaded, so errors can't
+		// happen here
+
+		let log_section = |section: & str, settings: & [Setting]| log::debug (format_args! (
+			"file={:?} section={:?} keys={}",
+			filename,
+			section,
+			settings.iter ()
+				.map (|setting| setting.key.as_str ())
+				.collect::<Vec <_>> ()
+				.join (",")));
+
+		for (filetype, settings) in & config_file.filetypes {
+			if self.filetype.as_deref () == Some (filetype.as_str ()) {
+				log_section (& format! ("filetype.{}", filetype), settings);
+				self.apply (settings).ok ();
+			}
+		}
+
+		let file_name = Path::new (filename).file_name ()
+			.and_then (|file_name| file_name.to_str ())
+			.unwrap_or (filename);
+
+		for glob_section in & config_file.globs {
+			if glob_section.glob.is_match (file_name) {
+				log_section (
+					& format! ("glob.{}", glob_section.glob.pattern ()),
+					& glob_section.settings);
+				self.apply (& glob_section.settings).ok ();
+			}
+		}
+
+		if let Some (relative_path) = config_file.relative_path (filename) {
+			for path_section in & config_file.paths {
+				if path_section.glob.is_match (& relative_path) {
+					log_section (
+						& format! ("path.{}", path_section.glob.pattern ()),
+						& path_section.settings);
+					self.apply (& path_section.settings).ok ();
+				}
+			}
+		}
+
+	}
+
+	/// Width of each level of indentation, which is the tab size unless it
+	/// was given separately.
+	pub fn indent_size (
+		& self,
+	) -> usize {
+		self.indent_size.unwrap_or (self.tab_size)
+	}
+
+	/// Where tabs advance to, from the tab stops if given or the tab size.
+	pub fn tab_stops (
+		& self,
+	) -> TabStops {
+		TabStops::new (& self.tab_stops, self.tab_size)
+	}
+
+	/// Whether a line matches one of the patterns for lines to leave alone.
+	pub fn is_line_ignored (
+		& self,
+		content: & str,
+	) -> bool {
+		self.ignore_line_pattern.iter ().any (|pattern| pattern.is_match (content))
+	}
+
+	/// Whether a rule should run, given the enabled and disabled rules.
+	pub fn is_rule_enabled (
+		& self,
+		id: & str,
+	) -> bool {
+
+		(self.enable.is_empty () || self.enable.iter ().any (|enabled| enabled == id))
+			&& ! self.disable.iter ().any (|disabled| disabled == id)
+			&& self.rule_severity (id) != Severity::Off
+
+	}
+
+	/// How serious problems found by a rule are, which is an error unless
+	/// set otherwise.
+	pub fn rule_severity (
+		& self,
+		id: & str,
+	) -> Severity {
+
+		self.severity.iter ().rev ()
+			.find (|rule_severity| rule_severity.rule == id)
+			.map_or (Severity::Error, |rule_severity| rule_severity.severity)
+
+	}
+
+	/// Change a setting, using the same name as the long command line option.
+	pub fn set (
+		& mut self,
+		key: & str,
+		value: & Value,
+	) -> Result <(), String> {
+
+		match key {
+			"expand-tabs" => self.expand_tabs = value_bool (value) ?,
+			"indent" => self.indent = value_enum (value) ?,
+			"smart-tabs" => self.smart_tabs = value_bool (value) ?,
+			"tab-size" => self.tab_size = value_usize (value) ?,
+			"tab-stops" => self.tab_stops = value_usizes (value) ?,
+			"indent-size" => self.indent_size = Some (value_usize (value) ?),
+			"normalise-indent" => self.normalise_indent = value_bool (value) ?,
+			"line-length" => self.line_length = value_usize (value) ?,
+			"line-length-ignore-urls" =>
+				self.line_length_options.ignore_urls = value_bool (value) ?,
+			"line-length-ignore-unbreakable" =>
+				self.line_length_options.ignore_unbreakable = value_bool (value) ?,
+			"line-length-wrap" =>
+				self.line_length_options.wrap = value_bool (value) ?,
+			"line-length-wrap-filetypes" =>
+				self.line_length_options.wrap_filetypes = value_strings (value) ?,
+			"line-ending" => self.line_ending = value_enum (value) ?,
+			"final-newline" => self.final_newline = value_bool (value) ?,
+			"unicode-whitespace" => self.unicode_whitespace = value_enum (value) ?,
+			"control-characters" => self.control_characters = value_enum (value) ?,
+			"form-feeds" => self.form_feeds = value_enum (value) ?,
+			"vertical-tabs" => self.vertical_tabs = value_enum (value) ?,
+			"markdown-line-breaks" => self.markdown_line_breaks = value_enum (value) ?,
+			"max-blank-lines" => self.max_blank_lines = Some (value_usize (value) ?),
+			"include-generated" => self.include_generated = value_bool (value) ?,
+			"enable" => self.enable = value_strings (value) ?,
+			"disable" => self.disable = value_strings (value) ?,
+			"ignore-line-pattern" => self.ignore_line_pattern.extend (
+				value_strings (value) ?.iter ()
+					.map (|value| Regex::new (value)
+						.map_err (|error| format! ("Invalid pattern: {}", error)))
+					.collect::<Result <Vec <_>, _>> () ?),
+			"severity" => self.severity.extend (
+				value_strings (value) ?.iter ()
+					.map (|value| value.parse ())
+					.collect::<Result <Vec <_>, _>> () ?),
+			_ if key.starts_with ("severity.") => self.severity.push (RuleSeverity {
+				rule: key ["severity.".len () .. ].to_owned (),
+				severity: value_enum (value) ?,
+			}),
+			_ => return Err (format! ("Unknown setting: {}", key)),
+		}
+
+		Ok (())
+
+	}
+
+	/// Change several settings, eg from a config file section.
+	pub fn apply <'a> (
+		& mut self,
+		settings: impl IntoIterator <Item = & 'a Setting>,
+	) -> Result <(), String> {
+
+		for setting in settings {
+			if let Err (error) = self.set (& setting.key, & setting.value) {
+				return Err (format! ("{}: {}", setting.line_number, error));
+			}
+		}
+
+		Ok (())
+
+	}
+
+}
+
+/// The same settings as the defaults for the command line options, for use
+/// as a library.
+impl Default for Config {
+
+	fn default () -> Config {
+
+		Config {
+			expand_tabs: false,
+			indent: Indent::Configured,
+			smart_tabs: false,
+			tab_size: 4,
+			tab_stops: Vec::new (),
+			indent_size: None,
+			normalise_indent: false,
+			line_length: 80,
+			line_length_options: LineLengthOptions::default (),
+			line_ending: LineEnding::Lf,
+			final_newline: false,
+			unicode_whitespace: UnicodeWhitespace::Report,
+			control_characters: ControlCharacters::Report,
+			form_feeds: FormFeeds::Report,
+			vertical_tabs: VerticalTabs::Report,
+			markdown_line_breaks: MarkdownLineBreaks::Preserve,
+			max_blank_lines: None,
+			include_generated: false,
+			enable: Vec::new (),
+			disable: Vec::new (),
+			severity: Vec::new (),
+			ignore_line_pattern: Vec::new (),
+			filetype: None,
+			line_ranges: None,
+		}
+
+	}
+
+}
+
+fn value_bool (
+	value: & Value,
+) -> Result <bool, String> {
+
+	match value {
+		Value::Bool (value) => Ok (* value),
+		_ => Err (format! ("Expected true or false but got {:?}", value)),
+	}
+
+}
+
+fn value_usize (
+	value: & Value,
+) -> Result <usize, String> {
+
+	match value {
+		& Value::Integer (value) if value >= 0 => Ok (value as usize),
+		_ => Err (format! ("Expected a positive integer but got {:?}", value)),
+	}
+
+}
+
+fn value_usizes (
+	value: & Value,
+) -> Result <Vec <usize>, String> {
+
+	match value {
+		Value::Array (values) => values.iter ().map (value_usize).collect (),
+		_ => Err (format! ("Expected a list of numbers but got {:?}", value)),
+	}
+
+}
+
+fn value_strings (
+	value: & Value,
+) -> Result <Vec <String>, String> {
+
+	match value {
+		Value::String (value) => Ok (vec! [ value.clone () ]),
+		Value::Array (values) => values.iter ()
+			.map (|value| match value {
+				Value::String (value) => Ok (value.clone ()),
+				_ => Err (format! ("Expected a string but got {:?}", value)),
+			})
+			.collect (),
+		_ => Err (format! ("Expected a list of strings but got {:?}", value)),
+	}
+
+}
+
+fn value_enum <Type: Choice> (
+	value: & Value,
+) -> Result <Type, String> {
+
+	match value {
+		Value::String (value) => Type::from_name (value, false),
+		_ => Err (format! ("Expected a string but got {:?}", value)),
+	}
+
+}
+
+/// Settings with a fixed set of values, named as on the command line.
+pub trait Choice: Copy + 'static {
+
+	const NAMES: & 'static [(& 'static str, Self)];
+
+	fn from_name (
+		name: & str,
+		ignore_case: bool,
+	) -> Result <Self, String> {
+
+		Self::NAMES.iter ()
+			.find (|& & (candidate, _)| if ignore_case {
+				candidate.eq_ignore_ascii_case (name)
+			} else {
+				candidate == name
+			})
+			.map (|& (_, value)| value)
+			.ok_or_else (|| format! ("Invalid variant: {}", name))
+
+	}
+
+}
+
+/// How problems found by a rule affect the result.
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum Severity {
+
+	/// Problems which can't be fixed cause a non-zero exit status.
+	Error,
+
+	/// Problems are reported and fixed, but don't affect the exit status.
+	Warning,
+
+	/// The rule doesn't run.
+	Off,
+
+}
+
+impl Choice for Severity {
+	const NAMES: & 'static [(& 'static str, Severity)] = & [
+		("error", Severity::Error),
+		("warning", Severity::Warning),
+		("off", Severity::Off),
+	];
+}
+
+/// A severity for a particular rule, eg `line-length=warning`.
+#[ derive (Clone, Debug, PartialEq, Eq) ]
+pub struct RuleSeverity {
+	pub rule: String,
+	pub severity: Severity,
+}
+
+impl FromStr for RuleSeverity {
+
+	type Err = String;
+
+	fn from_str (
+		value: & str,
+	) -> Result <RuleSeverity, String> {
+
+		let (rule, severity) = match value.split_once ('=') {
+			Some (parts) => parts,
+			None => return Err (format! ("Expected rule=level but got {}", value)),
+		};
+
+		Ok (RuleSeverity {
+			rule: rule.trim ().to_owned (),
+			severity: Severity::from_name (severity.trim (), false) ?,
+		})
+
+	}
+
+}
+
+#[ derive (Clone, Debug) ]
+#[ cfg_attr (feature = "cli", derive (clap::Args)) ]
+pub struct LineLengthOptions {
+
+	#[ cfg_attr (feature = "cli", clap (long = "line-length-ignore-urls")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_LINE_LENGTH_IGNORE_URLS")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Don't report long lines which contain a URL")) ]
+	pub ignore_urls: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long = "line-length-ignore-unbreakable")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_LINE_LENGTH_IGNORE_UNBREAKABLE")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Don't report long lines with no whitespace beyond the limit")) ]
+	pub ignore_unbreakable: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long = "line-length-wrap")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_LINE_LENGTH_WRAP")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "Wrap long lines of prose at word boundaries, eg in markdown or text files")) ]
+	pub wrap: bool,
+
+	#[ cfg_attr (feature = "cli", clap (long = "line-length-wrap-filetypes", value_name = "FILETYPES")) ]
+	#[ cfg_attr (feature = "cli", clap (use_value_delimiter = true, default_value = "text,markdown,gitcommit")) ]
+	#[ cfg_attr (feature = "cli", clap (env = "FIX_WHITESPACE_LINE_LENGTH_WRAP_FILETYPES")) ]
+	#[ cfg_attr (feature = "cli", clap (help = "File types which are prose, and can have long lines wrapped")) ]
+	pub wrap_filetypes: Vec <String>,
+
+}
+
+impl Default for LineLengthOptions {
+
+	fn default () -> LineLengthOptions {
+
+		LineLengthOptions {
+			ignore_urls: false,
+			ignore_unbreakable: false,
+			wrap: false,
+			wrap_filetypes: vec! [
+				"text".to_owned (),
+				"markdown".to_owned (),
+				"gitcommit".to_owned (),
+			],
+		}
+
+	}
+
+}
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum Indent {
+	Configured,
+	Auto,
+}
+
+impl Choice for Indent {
+	const NAMES: & 'static [(& 'static str, Indent)] = & [
+		("configured", Indent::Configured),
+		("auto", Indent::Auto),
+	];
+}
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum LineEnding {
+	Lf,
+	Crlf,
+	Cr,
+	Native,
+	Keep,
+}
+
+impl Choice for LineEnding {
+	const NAMES: & 'static [(& 'static str, LineEnding)] = & [
+		("lf", LineEnding::Lf),
+		("crlf", LineEnding::Crlf),
+		("cr", LineEnding::Cr),
+		("native", LineEnding::Native),
+		("keep", LineEnding::Keep),
+	];
+}
+
+impl LineEnding {
+
+	pub fn target (
+		self,
+	) -> Option <& 'static str> {
+
+		match self {
+			LineEnding::Lf => Some ("\n"),
+			LineEnding::Crlf => Some ("\r\n"),
+			LineEnding::Cr => Some ("\r"),
+			LineEnding::Native if cfg! (windows) => Some ("\r\n"),
+			LineEnding::Native => Some ("\n"),
+			LineEnding::Keep => None,
+		}
+
+	}
+
+}
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum UnicodeWhitespace {
+	Report,
+	Replace,
+	Remove,
+}
+
+impl Choice for UnicodeWhitespace {
+	const NAMES: & 'static [(& 'static str, UnicodeWhitespace)] = & [
+		("report", UnicodeWhitespace::Report),
+		("replace", UnicodeWhitespace::Replace),
+		("remove", UnicodeWhitespace::Remove),
+	];
+}
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum ControlCharacters {
+	Report,
+	Strip,
+	Replace,
+}
+
+impl Choice for ControlCharacters {
+	const NAMES: & 'static [(& 'static str, ControlCharacters)] = & [
+		("report", ControlCharacters::Report),
+		("strip", ControlCharacters::Strip),
+		("replace", ControlCharacters::Replace),
+	];
+}
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum FormFeeds {
+	Report,
+	Remove,
+	BlankLine,
+}
+
+impl Choice for FormFeeds {
+	const NAMES: & 'static [(& 'static str, FormFeeds)] = & [
+		("report", FormFeeds::Report),
+		("remove", FormFeeds::Remove),
+		("blank-line", FormFeeds::BlankLine),
+	];
+}
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum VerticalTabs {
+	Report,
+	Remove,
+}
+
+impl Choice for VerticalTabs {
+	const NAMES: & 'static [(& 'static str, VerticalTabs)] = & [
+		("report", VerticalTabs::Report),
+		("remove", VerticalTabs::Remove),
+	];
+}
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum MarkdownLineBreaks {
+	Preserve,
+	Backslash,
+	Remove,
+}
+
+impl Choice for MarkdownLineBreaks {
+	const NAMES: & 'static [(& 'static str, MarkdownLineBreaks)] = & [
+		("preserve", MarkdownLineBreaks::Preserve),
+		("backslash", MarkdownLineBreaks::Backslash),
+		("remove", MarkdownLineBreaks::Remove),
+	];
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	#[ cfg (feature = "cli") ]
+	use clap::Args as _;
+	#[ cfg (feature = "cli") ]
+	use clap::FromArgMatches as _;
+
+	use super::*;
+
+	#[ cfg (feature = "cli") ]
+	fn assert_names_match_command_line <Type> ()
+	where Type: Choice + clap::ArgEnum + PartialEq + std::fmt::Debug {
+
+		let names: Vec <_> = Type::value_variants ().iter ()
+			.map (|& value| (value.to_possible_value ().unwrap ().get_name (), value))
+			.collect ();
+
+		assert_eq! (names, Type::NAMES);
+
+	}
+
+	#[ cfg (feature = "cli") ]
+	#[ test ]
+	fn names_match_command_line () {
+
+		assert_names_match_command_line::<Severity> ();
+		assert_names_match_command_line::<Indent> ();
+		assert_names_match_command_line::<LineEnding> ();
+		assert_names_match_command_line::<UnicodeWhitespace> ();
+		assert_names_match_command_line::<ControlCharacters> ();
+		assert_names_match_command_line::<FormFeeds> ();
+		assert_names_match_command_line::<VerticalTabs> ();
+		assert_names_match_command_line::<MarkdownLineBreaks> ();
+		assert_names_match_command_line::<log::Level> ();
+
+	}
+
+	#[ cfg (feature = "cli") ]
+	#[ test ]
+	fn default_matches_command_line () {
+
+		let command = Config::augment_args (clap::Command::new ("test"));
+		let matches = command.get_matches_from (["test"]);
+		let parsed = Config::from_arg_matches (& matches).unwrap ();
+
+		assert_eq! (format! ("{:?}", Config::default ()), format! ("{:?}", parsed));
+
+	}
+
+	#[ test ]
+	fn sections_apply_in_order () {
+
+		let config_file = ConfigFile::parse (Path::new ("/project/.fix-whitespace.toml"), concat! (
+			"[filetype.python]\n",
+			"tab-size = 8\n",
+			"line-length = 100\n",
+			"[glob.\"*.py\"]\n",
+			"tab-size = 6\n",
+			"[path.\"vendor/**\"]\n",
+			"tab-size = 2\n",
+		)).unwrap ();
+
+		let for_file = |filename: & str|
+			Config::default ().for_file (Some (& config_file), filename, "");
+
+		assert_eq! (for_file ("/project/vendor/a.py").tab_size, 2);
+		assert_eq! (for_file ("/project/src/a.py").tab_size, 6);
+		assert_eq! (for_file ("/project/src/a.py").line_length, 100);
+		assert_eq! (for_file ("/project/src/a.rs").tab_size, 4);
+		assert_eq! (for_file ("/project/src/a.rs").filetype.as_deref (), Some ("rust"));
+
+	}
+
+	#[ test ]
+	fn modeline_takes_precedence () {
+
+		let config = Config { indent: Indent::Auto, ..Config::default () };
+
+		let contents = "a\n  b\n    c\n";
+		let detected = config.for_file (None, "a.txt", contents);
+
+		assert! (detected.expand_tabs);
+		assert_eq! (detected.tab_size, 2);
+		assert_eq! (detected.indent_size, Some (2));
+
+		let contents = "a\n  b\n    c\n# vim: noet ts=8 sw=4 ft=python\n";
+		let from_modeline = config.for_file (None, "a.txt", contents);
+
+		assert! (! from_modeline.expand_tabs);
+		assert_eq! (from_modeline.tab_size, 8);
+		assert_eq! (from_modeline.indent_size (), 4);
+		assert_eq! (from_modeline.filetype.as_deref (), Some ("python"));
+
+	}
+
+	#[ test ]
+	fn sets_values () {
+
+		let mut config = Config::default ();
+
+		config.set ("line-ending", & Value::String ("crlf".to_owned ())).unwrap ();
+		config.set ("max-blank-lines", & Value::Integer (2)).unwrap ();
+		config.set ("disable", & Value::Array (vec! [ Value::String ("tabs".to_owned ()) ])).unwrap ();
+
+		assert_eq! (config.line_ending, LineEnding::Crlf);
+		assert_eq! (config.max_blank_lines, Some (2));
+		assert! (! config.is_rule_enabled ("tabs"));
+
+		assert_eq! (
+			config.set ("colour", & Value::Bool (true)),
+			Err ("Unknown setting: colour".to_owned ()));
+
+		assert_eq! (
+			config.set ("tab-size", & Value::Integer (-1)),
+			Err ("Expected a positive integer but got Integer(-1)".to_owned ()));
+
+		assert! (config.set ("line-ending", & Value::String ("dos".to_owned ())).is_err ());
+
+	}
+
+	#[ test ]
+	fn patterns_and_severities_add_up () {
+
+		let mut config = Config::default ();
+
+		config.set ("ignore-line-pattern", & Value::String ("a".to_owned ())).unwrap ();
+		config.set (
+			"ignore-line-pattern",
+			& Value::Array (vec! [ Value::String ("b".to_owned ()) ])).unwrap ();
+
+		assert! (config.is_line_ignored ("xa"));
+		assert! (config.is_line_ignored ("xb"));
+		assert! (! config.is_line_ignored ("xc"));
+
+		config.set ("severity", & Value::String ("tabs=warning".to_owned ())).unwrap ();
+		config.set ("severity.blank-lines", & Value::String ("off".to_owned ())).unwrap ();
+		config.set ("severity.tabs", & Value::String ("error".to_owned ())).unwrap ();
+
+		assert_eq! (config.rule_severity ("tabs"), Severity::Error);
+		assert_eq! (config.rule_severity ("blank-lines"), Severity::Off);
+		assert_eq! (config.rule_severity ("other"), Severity::Error);
+		assert! (! config.is_rule_enabled ("blank-lines"));
+
+		assert! (config.set ("severity", & Value::String ("tabs".to_owned ())).is_err ());
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,659 @@
+//! Configuration file support.
+//!
+//! The configuration file uses a subset of TOML. Top level keys set defaults
+//! for the whole run, and `[filetype.<name>]`, `[glob."<pattern>"]` and
+//! `[path."<pattern>"]` sections override them for matching files. Keys are
+//! the same as the long command line options, for example `line-length = 100`.
+//!
+//! Glob sections match the file name, while path sections match the path
+//! relative to the directory containing the configuration file, so they can
+//! apply to a whole subtree such as `vendor/**`.
+//!
+//! Custom rules are declared in `[rule.<id>]` sections, with a `pattern`
+//! regex, a `message`, and optionally a `replacement` to fix matches and a
+//! list of `filetypes` to restrict it to.
+
+#[ cfg (feature = "fs") ]
+use std::env;
+#[ cfg (feature = "fs") ]
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+#[ cfg (feature = "fs") ]
+use crate::error::Error;
+use crate::glob::Glob;
+use crate::paths::absolute;
+use crate::rules::RegexRule;
+
+pub const CONFIG_FILE_NAME: & str = ".fix-whitespace.toml";
+
+#[ derive (Clone, Debug, PartialEq) ]
+pub enum Value {
+	Bool (bool),
+	Integer (i64),
+	String (String),
+	Array (Vec <Value>),
+}
+
+pub struct Setting {
+	pub key: String,
+	pub value: Value,
+	pub line_number: usize,
+}
+
+pub struct GlobSection {
+	pub glob: Glob,
+	pub settings: Vec <Setting>,
+}
+
+pub struct ConfigFile {
+	pub path: PathBuf,
+	pub settings: Vec <Setting>,
+	pub filetypes: Vec <(String, Vec <Setting>)>,
+	pub globs: Vec <GlobSection>,
+	pub paths: Vec <GlobSection>,
+	pub rules: Vec <RegexRule>,
+}
+
+impl ConfigFile {
+
+	/// Look for a configuration file in the current directory and its parents.
+	#[ cfg (feature = "fs") ]
+	pub fn find () -> Result <Option <ConfigFile>, Error> {
+
+		let current_dir = match env::current_dir () {
+			Ok (current_dir) => current_dir,
+			Err (error) => return Err (Error::io ("reading", "current directory", error)),
+		};
+
+		for dir in current_dir.ancestors () {
+			let path = dir.join (CONFIG_FILE_NAME);
+			if path.is_file () {
+				return ConfigFile::load (& path).map (Some);
+			}
+		}
+
+		Ok (None)
+
+	}
+
+	#[ cfg (feature = "fs") ]
+	pub fn load (
+		path: & Path,
+	) -> Result <ConfigFile, Error> {
+
+		let contents = match fs::read_to_string (path) {
+			Ok (contents) => contents,
+			Err (error) => return Err (Error::io ("reading", path.display ().to_string (), error)),
+		};
+
+		ConfigFile::parse (path, & contents)
+			.map_err (|message| Error::Config { path: path.to_owned (), message })
+
+	}
+
+	/// Parse a configuration file which has already been read. The path is
+	/// only used to resolve `[path."<pattern>"]` sections.
+	pub fn parse (
+		path: & Path,
+		contents: & str,
+	) -> Result <ConfigFile, String> {
+
+		let mut config_file = ConfigFile {
+			path: path.to_owned (),
+			settings: Vec::new (),
+			filetypes: Vec::new (),
+			globs: Vec::new (),
+			paths: Vec::new (),
+			rules: Vec::new (),
+		};
+
+		let mut rule_sections: Vec <(String, usize, Vec <Setting>)> = Vec::new ();
+		let mut section = & mut config_file.settings;
+		let mut key_prefix = "";
+		let mut lines = contents.lines ().enumerate ();
+
+		while let Some ((line_index, line)) = lines.next () {
+
+			let line_number = line_index + 1;
+			let error = |message: String| format! ("{}: {}", line_number, message);
+
+			let mut line = strip_comment (line).trim ().to_owned ();
+
+			// arrays can continue over several lines
+
+			while line.contains ("= [") && ! is_array_closed (& line) {
+				match lines.next () {
+					Some ((_, next_line)) => {
+						line.push (' ');
+						line.push_str (strip_comment (next_line).trim ());
+					},
+					None => return Err (error ("Unterminated array".to_owned ())),
+				}
+			}
+
+			if line.is_empty () {
+				continue;
+			}
+
+			if let Some (header) =
+					line.strip_prefix ('[').and_then (|line| line.strip_suffix (']')) {
+
+				let header = parse_key_path (header).map_err (error) ?;
+
+				key_prefix = "";
+
+				section = match header.iter ().map (String::as_str).collect::<Vec <_>> () [..] {
+
+					["filetype", filetype] => {
+						config_file.filetypes.push ((filetype.to_owned (), Vec::new ()));
+						& mut config_file.filetypes.last_mut ().unwrap ().1
+					},
+
+					["rule", id] => {
+						rule_sections.push ((id.to_owned (), line_number, Vec::new ()));
+						& mut rule_sections.last_mut ().unwrap ().2
+					},
+
+					// severities can also be given as dotted keys anywhere
+
+					["severity"] => {
+						key_prefix = "severity.";
+						& mut config_file.settings
+					},
+
+					["glob", pattern] => {
+						config_file.globs.push (GlobSection {
+							glob: Glob::new (pattern).map_err (error) ?,
+							settings: Vec::new (),
+						});
+						& mut config_file.globs.last_mut ().unwrap ().settings
+					},
+
+					["path", pattern] => {
+						config_file.paths.push (GlobSection {
+							glob: Glob::new (pattern).map_err (error) ?,
+							settings: Vec::new (),
+						});
+						& mut config_file.paths.last_mut ().unwrap ().settings
+					},
+
+					_ => return Err (error (format! ("Unknown section [{}]", header.join (".")))),
+
+				};
+
+				continue;
+
+			}
+
+			let (key, value) = match line.split_once ('=') {
+				Some ((key, value)) => (key.trim (), value.trim ()),
+				None => return Err (error (format! ("Expected key = value: {}", line))),
+			};
+
+			let key = match parse_key_path (key).map_err (error) ? [..] {
+				[ref key] => format! ("{}{}", key_prefix, key),
+				[ref prefix, ref key] if prefix == "severity" && key_prefix.is_empty () =>
+					format! ("severity.{}", key),
+				_ => return Err (error (format! ("Invalid key: {}", key))),
+			};
+
+			section.push (Setting {
+				key,
+				value: parse_value (value).map_err (error) ?,
+				line_number,
+			});
+
+		}
+
+		for (id, line_number, settings) in rule_sections {
+			config_file.rules.push (
+				regex_rule (& id, & settings)
+					.map_err (|error| format! ("{}: {}", line_number, error)) ?);
+		}
+
+		Ok (config_file)
+
+	}
+
+	/// Path of a file relative to the directory containing the config file,
+	/// with `/` separators, or `None` if it's outside that directory.
+	pub fn relative_path (
+		& self,
+		filename: & str,
+	) -> Option <String> {
+
+		let directory = absolute (self.path.parent () ?);
+		let path = absolute (Path::new (filename));
+
+		let components: Vec <& str> = path.strip_prefix (& directory).ok () ?
+			.components ()
+			.map (|component| component.as_os_str ().to_str ())
+			.collect::<Option <_>> () ?;
+
+		Some (components.join ("/"))
+
+	}
+
+}
+
+fn regex_rule (
+	id: & str,
+	settings: & [Setting],
+) -> Result <RegexRule, String> {
+
+	let mut pattern = None;
+	let mut replacement = None;
+	let mut message = None;
+	let mut filetypes = Vec::new ();
+
+	for setting in settings {
+
+		let string_value = || match setting.value {
+			Value::String (ref value) => Ok (value.clone ()),
+			ref value => Err (format! ("Expected a string but got {:?}", value)),
+		};
+
+		match setting.key.as_str () {
+			"pattern" => pattern = Some (string_value () ?),
+			"replacement" => replacement = Some (string_value () ?),
+			"message" => message = Some (string_value () ?),
+			"filetypes" => match setting.value {
+				Value::Array (ref values) => for value in values {
+					match value {
+						Value::String (value) => filetypes.push (value.clone ()),
+						value => return Err (format! ("Expected a string but got {:?}", value)),
+					}
+				},
+				ref value => return Err (format! ("Expected an array but got {:?}", value)),
+			},
+			key => return Err (format! ("Unknown rule setting: {}", key)),
+		}
+
+	}
+
+	let pattern = match pattern {
+		Some (pattern) => pattern,
+		None => return Err (format! ("Rule {} has no pattern", id)),
+	};
+
+	let message = message.unwrap_or_else (|| format! ("matched {}", id));
+
+	RegexRule::new (id, & pattern, replacement, message, filetypes)
+
+}
+
+fn is_array_closed (
+	line: & str,
+) -> bool {
+
+	let mut depth = 0;
+	let mut quote = None;
+	let mut escaped = false;
+
+	for character in line.chars () {
+
+		if escaped {
+			escaped = false;
+			continue;
+		}
+
+		match (quote, character) {
+			(None, '"' | '\'') => quote = Some (character),
+			(None, '[') => depth += 1,
+			(None, ']') => depth -= 1,
+			(Some ('"'), '\\') => escaped = true,
+			(Some (quote_character), _) if character == quote_character => quote = None,
+			_ => (),
+		}
+
+	}
+
+	depth <= 0
+
+}
+
+fn strip_comment (
+	line: & str,
+) -> & str {
+
+	let mut quote = None;
+	let mut escaped = false;
+
+	for (index, character) in line.char_indices () {
+
+		// only basic strings, in double quotes, have escapes
+
+		if escaped {
+			escaped = false;
+			continue;
+		}
+
+		match (quote, character) {
+			(None, '#') => return & line [ .. index],
+			(None, '"' | '\'') => quote = Some (character),
+			(Some ('"'), '\\') => escaped = true,
+			(Some (quote_character), _) if character == quote_character => quote = None,
+			_ => (),
+		}
+
+	}
+
+	line
+
+}
+
+fn parse_key_path (
+	source: & str,
+) -> Result <Vec <String>, String> {
+
+	let mut keys = Vec::new ();
+	let mut remaining = source.trim ();
+
+	loop {
+
+		let (key, rest) = if remaining.starts_with (['"', '\'']) {
+			parse_string (remaining) ?
+		} else {
+			let key_end = remaining.find (|character: char|
+				! (character.is_ascii_alphanumeric () || character == '-' || character == '_'))
+				.unwrap_or (remaining.len ());
+			if key_end == 0 {
+				return Err (format! ("Invalid key: {}", source));
+			}
+			(remaining [ .. key_end].to_owned (), & remaining [key_end .. ])
+		};
+
+		keys.push (key);
+
+		remaining = rest.trim_start ();
+
+		if remaining.is_empty () {
+			return Ok (keys);
+		}
+
+		match remaining.strip_prefix ('.') {
+			Some (rest) => remaining = rest.trim_start (),
+			None => return Err (format! ("Invalid key: {}", source)),
+		}
+
+	}
+
+}
+
+fn parse_string (
+	source: & str,
+) -> Result <(String, & str), String> {
+
+	let mut chars = source.char_indices ();
+	let quote = chars.next ().map (|(_, quote)| quote);
+	let mut value = String::new ();
+
+	while let Some ((index, character)) = chars.next () {
+
+		if Some (character) == quote {
+			return Ok ((value, & source [index + 1 .. ]));
+		}
+
+		if character == '\\' && quote == Some ('"') {
+			match chars.next () {
+				Some ((_, 'n')) => value.push ('\n'),
+				Some ((_, 't')) => value.push ('\t'),
+				Some ((_, 'r')) => value.push ('\r'),
+				Some ((_, '\\')) => value.push ('\\'),
+				Some ((_, '"')) => value.push ('"'),
+				_ => return Err (format! ("Invalid escape in string: {}", source)),
+			}
+			continue;
+		}
+
+		value.push (character);
+
+	}
+
+	Err (format! ("Unterminated string: {}", source))
+
+}
+
+fn parse_value (
+	source: & str,
+) -> Result <Value, String> {
+
+	if source.starts_with (['"', '\'']) {
+		return match parse_string (source) ? {
+			(value, "") => Ok (Value::String (value)),
+			_ => Err (format! ("Invalid value: {}", source)),
+		};
+	}
+
+	if let Some (items) =
+			source.strip_prefix ('[').and_then (|source| source.strip_suffix (']')) {
+		return parse_array (items);
+	}
+
+	match source {
+		"true" => return Ok (Value::Bool (true)),
+		"false" => return Ok (Value::Bool (false)),
+		_ => (),
+	}
+
+	match source.replace ('_', "").parse::<i64> () {
+		Ok (value) => Ok (Value::Integer (value)),
+		Err (_) => Err (format! ("Invalid value: {}", source)),
+	}
+
+}
+
+fn parse_array (
+	source: & str,
+) -> Result <Value, String> {
+
+	let mut values = Vec::new ();
+	let mut remaining = source.trim ();
+
+	while ! remaining.is_empty () {
+
+		let rest = if remaining.starts_with (['"', '\'']) {
+			let (value, rest) = parse_string (remaining) ?;
+			values.push (Value::String (value));
+			rest
+		} else {
+			let value_end = remaining.find (',').unwrap_or (remaining.len ());
+			values.push (parse_value (remaining [ .. value_end].trim ()) ?);
+			& remaining [value_end .. ]
+		};
+
+		remaining = rest.trim_start ();
+
+		match remaining.strip_prefix (',') {
+			Some (rest) => remaining = rest.trim_start (),
+			None if remaining.is_empty () => (),
+			None => return Err (format! ("Invalid array: [{}]", source)),
+		}
+
+	}
+
+	Ok (Value::Array (values))
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	fn parse (contents: & str) -> Result <ConfigFile, String> {
+		ConfigFile::parse (Path::new ("/project/.fix-whitespace.toml"), contents)
+	}
+
+	fn values (settings: & [Setting]) -> Vec <(& str, & Value)> {
+		settings.iter ().map (|setting| (setting.key.as_str (), & setting.value)).collect ()
+	}
+
+	#[ test ]
+	fn parses_values () {
+
+		let config_file = parse (concat! (
+			"# comment\n",
+			"line-length = 1_000\n",
+			"expand-tabs = true # trailing comment\n",
+			"line-ending = \"crlf\"\n",
+			"ignore-line-pattern = 'a#b'\n",
+			"disable = [ \"blank-lines\", 'tabs', ]\n",
+		)).unwrap ();
+
+		assert_eq! (values (& config_file.settings), vec! [
+			("line-length", & Value::Integer (1000)),
+			("expand-tabs", & Value::Bool (true)),
+			("line-ending", & Value::String ("crlf".to_owned ())),
+			("ignore-line-pattern", & Value::String ("a#b".to_owned ())),
+			("disable", & Value::Array (vec! [
+				Value::String ("blank-lines".to_owned ()),
+				Value::String ("tabs".to_owned ()),
+			])),
+		]);
+
+		assert_eq! (config_file.settings [1].line_number, 3);
+
+	}
+
+	#[ test ]
+	fn parses_escapes () {
+
+		let config_file = parse ("a = \"x\\t\\\"y\\\\\"\nb = 'x\\t'\n").unwrap ();
+
+		assert_eq! (values (& config_file.settings), vec! [
+			("a", & Value::String ("x\t\"y\\".to_owned ())),
+			("b", & Value::String ("x\\t".to_owned ())),
+		]);
+
+	}
+
+	#[ test ]
+	fn escaped_quotes_dont_end_strings () {
+
+		let config_file = parse ("a = \"x\\\"#y\" # z\nb = 'x\\'#y\n").unwrap ();
+
+		assert_eq! (values (& config_file.settings), vec! [
+			("a", & Value::String ("x\"#y".to_owned ())),
+			("b", & Value::String ("x\\".to_owned ())),
+		]);
+
+		let config_file = parse ("c = [ \"\\\"]\",\n\t\"d\" ]\n").unwrap ();
+
+		assert_eq! (values (& config_file.settings), vec! [
+			("c", & Value::Array (vec! [
+				Value::String ("\"]".to_owned ()),
+				Value::String ("d".to_owned ()),
+			])),
+		]);
+
+	}
+
+	#[ test ]
+	fn parses_multiline_arrays () {
+
+		let config_file = parse ("disable = [\n\t\"a\", # first\n\t\"]\",\n]\nb = 1\n").unwrap ();
+
+		assert_eq! (values (& config_file.settings), vec! [
+			("disable", & Value::Array (vec! [
+				Value::String ("a".to_owned ()),
+				Value::String ("]".to_owned ()),
+			])),
+			("b", & Value::Integer (1)),
+		]);
+
+		assert_eq! (parse ("disable = [\n\"a\",\n").err (), Some ("1: Unterminated array".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn parses_sections () {
+
+		let config_file = parse (concat! (
+			"tab-size = 4\n",
+			"severity.tabs = \"error\"\n",
+			"[filetype.python]\n",
+			"tab-size = 8\n",
+			"[glob.\"*.md\"]\n",
+			"line-length = 0\n",
+			"[path.\"vendor/**\"]\n",
+			"disable-all = true\n",
+			"[severity]\n",
+			"blank-lines = \"info\"\n",
+		)).unwrap ();
+
+		assert_eq! (values (& config_file.settings), vec! [
+			("tab-size", & Value::Integer (4)),
+			("severity.tabs", & Value::String ("error".to_owned ())),
+			("severity.blank-lines", & Value::String ("info".to_owned ())),
+		]);
+
+		assert_eq! (config_file.filetypes.len (), 1);
+		assert_eq! (config_file.filetypes [0].0, "python");
+		assert_eq! (
+			values (& config_file.filetypes [0].1),
+			vec! [ ("tab-size", & Value::Integer (8)) ]);
+
+		assert_eq! (config_file.globs.len (), 1);
+		assert! (config_file.globs [0].glob.is_match ("README.md"));
+		assert_eq! (
+			values (& config_file.globs [0].settings),
+			vec! [ ("line-length", & Value::Integer (0)) ]);
+
+		assert_eq! (config_file.paths.len (), 1);
+		assert! (config_file.paths [0].glob.is_match ("vendor/a/b.c"));
+
+	}
+
+	#[ test ]
+	fn parses_rules () {
+
+		let config_file = parse (concat! (
+			"[rule.no-todo]\n",
+			"pattern = \"TODO\"\n",
+			"filetypes = [ \"rust\" ]\n",
+		)).unwrap ();
+
+		assert_eq! (config_file.rules.len (), 1);
+
+		assert_eq! (
+			parse ("[rule.empty]\nmessage = \"x\"\n").err (),
+			Some ("1: Rule empty has no pattern".to_owned ()));
+
+		assert_eq! (
+			parse ("[rule.bad]\npattern = \"x\"\ncolour = \"red\"\n").err (),
+			Some ("1: Unknown rule setting: colour".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn reports_errors_with_line_numbers () {
+
+		assert_eq! (parse ("\n[unknown]\n").err (), Some ("2: Unknown section [unknown]".to_owned ()));
+		assert_eq! (parse ("tab-size\n").err (), Some ("1: Expected key = value: tab-size".to_owned ()));
+		assert_eq! (parse ("a = 1.5\n").err (), Some ("1: Invalid value: 1.5".to_owned ()));
+		assert_eq! (parse ("a = \"x\n").err (), Some ("1: Unterminated string: \"x".to_owned ()));
+		assert_eq! (parse ("a = \"x\" y\n").err (), Some ("1: Invalid value: \"x\" y".to_owned ()));
+		assert_eq! (parse ("a.b = 1\n").err (), Some ("1: Invalid key: a.b".to_owned ()));
+		assert_eq! (parse ("a = [1 2]\n").err (), Some ("1: Invalid value: 1 2".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn relative_path () {
+
+		let config_file = parse ("").unwrap ();
+
+		let relative_path = |filename| config_file.relative_path (filename);
+
+		assert_eq! (relative_path ("/project/src/main.rs"), Some ("src/main.rs".to_owned ()));
+		assert_eq! (relative_path ("/project/src/../README.md"), Some ("README.md".to_owned ()));
+		assert_eq! (relative_path ("/other/main.rs"), None);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,410 @@
+//! Parsing unified diffs to find which lines have changed, and comparing two
+//! versions of a file to show what a fix changes.
+//!
+//! Only the file names and hunk headers are needed, so this works best with
+//! diffs generated without context, eg `git diff --unified=0`. Line numbers
+//! refer to the new version of each file.
+
+use std::ops::Range;
+use std::ops::RangeInclusive;
+
+/// A set of line numbers, starting from one.
+#[ derive (Clone, Debug, Default, PartialEq, Eq) ]
+pub struct LineRanges {
+	ranges: Vec <RangeInclusive <u64>>,
+}
+
+impl LineRanges {
+
+	pub fn new () -> LineRanges {
+		LineRanges::default ()
+	}
+
+	/// Every line, eg of a new file.
+	pub fn all () -> LineRanges {
+		LineRanges { ranges: vec! [ 1 ..= u64::MAX ] }
+	}
+
+	pub fn add (
+		& mut self,
+		first: u64,
+		count: u64,
+	) {
+
+		if count > 0 {
+			self.ranges.push (first ..= first + count - 1);
+		}
+
+	}
+
+	pub fn contains (
+		& self,
+		line_number: u64,
+	) -> bool {
+		self.ranges.iter ().any (|range| range.contains (& line_number))
+	}
+
+	pub fn is_empty (
+		& self,
+	) -> bool {
+		self.ranges.is_empty ()
+	}
+
+}
+
+/// Find the lines added or changed in each file in a diff. Files which were
+/// deleted are left out.
+pub fn changed_lines (
+	diff: & str,
+) -> Result <Vec <(String, LineRanges)>, String> {
+
+	let mut files: Vec <(String, LineRanges)> = Vec::new ();
+	let mut current_file = false;
+	let mut previous_line = "";
+
+	// lines in a hunk can look like headers, eg an added line starting with
+	// "++ ", so they are counted off rather than examined
+
+	let mut old_remaining: u64 = 0;
+	let mut new_remaining: u64 = 0;
+
+	for (line_index, line) in diff.lines ().enumerate () {
+
+		let error = |message: & str| format! ("{}: {}: {}", line_index + 1, message, line);
+
+		if old_remaining > 0 || new_remaining > 0 {
+
+			let in_hunk = match line.chars ().next () {
+				Some ('+') if new_remaining > 0 => {
+					new_remaining -= 1;
+					true
+				},
+				Some ('-') if old_remaining > 0 => {
+					old_remaining -= 1;
+					true
+				},
+				Some (' ') if old_remaining > 0 && new_remaining > 0 => {
+					old_remaining -= 1;
+					new_remaining -= 1;
+					true
+				},
+				Some ('\\') => true,
+				_ => false,
+			};
+
+			if in_hunk {
+				previous_line = line;
+				continue;
+			}
+
+			// the hunk was shorter than its header said
+
+			old_remaining = 0;
+			new_remaining = 0;
+
+		}
+
+		if let Some (path) = line.strip_prefix ("+++ ")
+				.filter (|_| previous_line.starts_with ("--- ")) {
+
+			let path = parse_path (path).ok_or_else (|| error ("Invalid file name")) ?;
+
+			current_file = path != "/dev/null";
+
+			if current_file {
+				let path = path.strip_prefix ("b/").unwrap_or (& path);
+				files.push ((path.to_owned (), LineRanges::new ()));
+			}
+
+		} else if let Some (header) = line.strip_prefix ("@@ ").filter (|_| current_file) {
+
+			let (old_count, first, count) = parse_hunk_header (header)
+				.ok_or_else (|| error ("Invalid hunk header")) ?;
+
+			files.last_mut ().unwrap ().1.add (first, count);
+
+			old_remaining = old_count;
+			new_remaining = count;
+
+		}
+
+		previous_line = line;
+
+	}
+
+	Ok (files)
+
+}
+
+/// A file name from a `---` or `+++` line. Git ends names containing spaces
+/// with a tab, and quotes names with special characters like a C string.
+fn parse_path (
+	path: & str,
+) -> Option <String> {
+
+	let quoted = match path.strip_prefix ('"') {
+		Some (quoted) => quoted,
+		None => return Some (path.strip_suffix ('\t').unwrap_or (path).to_owned ()),
+	};
+
+	let mut bytes = Vec::new ();
+	let mut chars = quoted.chars ();
+
+	loop {
+		match chars.next () ? {
+			'"' => break,
+			'\\' => match chars.next () ? {
+				'a' => bytes.push (0x07),
+				'b' => bytes.push (0x08),
+				't' => bytes.push (b'\t'),
+				'n' => bytes.push (b'\n'),
+				'v' => bytes.push (0x0b),
+				'f' => bytes.push (0x0c),
+				'r' => bytes.push (b'\r'),
+				digit @ '0' ..= '3' => {
+					let mut value = digit.to_digit (8) ?;
+					for _ in 0 .. 2 {
+						value = value * 8 + chars.next () ?.to_digit (8) ?;
+					}
+					bytes.push (value as u8);
+				},
+				character => bytes.extend (character.to_string ().as_bytes ()),
+			},
+			character => bytes.extend (character.to_string ().as_bytes ()),
+		}
+	}
+
+	String::from_utf8 (bytes).ok ()
+
+}
+
+/// A run of lines which differ between two versions, as indexes into each.
+#[ derive (Clone, Debug, PartialEq, Eq) ]
+pub struct Change {
+	pub old: Range <usize>,
+	pub new: Range <usize>,
+}
+
+/// Find the lines which differ between two versions. Fixes rarely add or
+/// remove lines, so when both versions have the same number of lines between
+/// their common start and end, these are compared one to one. Otherwise the
+/// whole of the middle is a single change.
+pub fn line_changes (
+	old: & [& str],
+	new: & [& str],
+) -> Vec <Change> {
+
+	let prefix = old.iter ().zip (new).take_while (|(old, new)| old == new).count ();
+
+	let suffix = old [prefix .. ].iter ().rev ()
+		.zip (new [prefix .. ].iter ().rev ())
+		.take_while (|(old, new)| old == new)
+		.count ();
+
+	let old_end = old.len () - suffix;
+	let new_end = new.len () - suffix;
+
+	if old_end - prefix != new_end - prefix {
+		return vec! [ Change { old: prefix .. old_end, new: prefix .. new_end } ];
+	}
+
+	let mut changes: Vec <Change> = Vec::new ();
+
+	for index in prefix .. old_end {
+
+		if old [index] == new [index] {
+			continue;
+		}
+
+		match changes.last_mut () {
+			Some (change) if change.old.end == index => {
+				change.old.end += 1;
+				change.new.end += 1;
+			},
+			_ => changes.push (Change { old: index .. index + 1, new: index .. index + 1 }),
+		}
+
+	}
+
+	changes
+
+}
+
+fn parse_hunk_header (
+	header: & str,
+) -> Option <(u64, u64, u64)> {
+
+	// the header looks like "-1,2 +3,4 @@", where the counts default to one
+
+	let range = |prefix: char| -> Option <(u64, u64)> {
+		let range = header.split (' ').find_map (|part| part.strip_prefix (prefix)) ?;
+		Some (match range.split_once (',') {
+			Some ((first, count)) => (first.parse ().ok () ?, count.parse ().ok () ?),
+			None => (range.parse ().ok () ?, 1),
+		})
+	};
+
+	let (_, old_count) = range ('-') ?;
+	let (first, count) = range ('+') ?;
+
+	Some ((old_count, first, count))
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	fn ranges (pairs: & [(u64, u64)]) -> LineRanges {
+		let mut ranges = LineRanges::new ();
+		for & (first, count) in pairs {
+			ranges.add (first, count);
+		}
+		ranges
+	}
+
+	#[ test ]
+	fn line_ranges () {
+
+		let ranges = ranges (& [ (3, 2), (10, 0), (20, 1) ]);
+
+		assert! (! ranges.contains (2));
+		assert! (ranges.contains (3));
+		assert! (ranges.contains (4));
+		assert! (! ranges.contains (5));
+		assert! (! ranges.contains (10));
+		assert! (ranges.contains (20));
+
+		assert! (LineRanges::all ().contains (1));
+		assert! (LineRanges::all ().contains (u64::MAX));
+
+		assert! (LineRanges::new ().is_empty ());
+		assert! (self::ranges (& [ (1, 0) ]).is_empty ());
+
+	}
+
+	#[ test ]
+	fn changed_lines_from_diff () {
+
+		let diff = concat! (
+			"diff --git a/src/a.rs b/src/a.rs\n",
+			"--- a/src/a.rs\n",
+			"+++ b/src/a.rs\n",
+			"@@ -1,2 +1,3 @@\n",
+			"+added\n",
+			"@@ -10 +11 @@\n",
+			"-old\n",
+			"+new\n",
+			"@@ -20,2 +21,0 @@\n",
+			"diff --git a/gone.rs b/gone.rs\n",
+			"--- a/gone.rs\n",
+			"+++ /dev/null\n",
+			"@@ -1,3 +0,0 @@\n",
+			"diff --git a/new.rs b/new.rs\n",
+			"--- /dev/null\n",
+			"+++ b/new.rs\n",
+			"@@ -0,0 +1,2 @@\n",
+		);
+
+		assert_eq! (changed_lines (diff), Ok (vec! [
+			("src/a.rs".to_owned (), ranges (& [ (1, 3), (11, 1) ])),
+			("new.rs".to_owned (), ranges (& [ (1, 2) ])),
+		]));
+
+	}
+
+	#[ test ]
+	fn invalid_hunk_header () {
+
+		assert_eq! (
+			changed_lines ("--- a/a.rs\n+++ b/a.rs\n@@ -1 +x @@\n"),
+			Err ("3: Invalid hunk header: @@ -1 +x @@".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn hunk_lines_which_look_like_headers () {
+
+		let diff = concat! (
+			"--- a/a.txt\n",
+			"+++ b/a.txt\n",
+			"@@ -1,2 +1,3 @@\n",
+			"---- a\n",
+			"+++ foo \n",
+			"+--- b\n",
+			"+c\n",
+			"\\ No newline at end of file\n",
+			"@@ -5,0 +6 @@\n",
+			"+@@ -1 +1 @@\n",
+		);
+
+		assert_eq! (changed_lines (diff), Ok (vec! [
+			("a.txt".to_owned (), ranges (& [ (1, 3), (6, 1) ])),
+		]));
+
+	}
+
+	#[ test ]
+	fn file_names_with_special_characters () {
+
+		let diff = concat! (
+			"--- a/sp ace.txt\t\n",
+			"+++ b/sp ace.txt\t\n",
+			"@@ -1 +1 @@\n",
+			"-a\n",
+			"+b\n",
+			"--- \"a/tab\\there\"\n",
+			"+++ \"b/tab\\there\"\n",
+			"@@ -1 +1 @@\n",
+			"-a\n",
+			"+b\n",
+			"--- \"a/q\\\"uote\\\\\\303\\251\"\n",
+			"+++ \"b/q\\\"uote\\\\\\303\\251\"\n",
+			"@@ -2 +2 @@\n",
+			"-a\n",
+			"+b\n",
+		);
+
+		assert_eq! (changed_lines (diff), Ok (vec! [
+			("sp ace.txt".to_owned (), ranges (& [ (1, 1) ])),
+			("tab\there".to_owned (), ranges (& [ (1, 1) ])),
+			("q\"uote\\é".to_owned (), ranges (& [ (2, 1) ])),
+		]));
+
+		assert_eq! (
+			changed_lines ("--- a/x\n+++ \"b/x\n"),
+			Err ("2: Invalid file name: +++ \"b/x".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn line_changes_one_to_one () {
+
+		assert_eq! (
+			line_changes (& [ "a", "b", "c", "d", "e" ], & [ "a", "B", "C", "d", "E" ]),
+			vec! [
+				Change { old: 1 .. 3, new: 1 .. 3 },
+				Change { old: 4 .. 5, new: 4 .. 5 },
+			]);
+
+		assert_eq! (line_changes (& [ "a" ], & [ "a" ]), Vec::new ());
+
+	}
+
+	#[ test ]
+	fn line_changes_added_lines () {
+
+		assert_eq! (
+			line_changes (& [ "a", "b", "c" ], & [ "a", "x", "y", "c" ]),
+			vec! [ Change { old: 1 .. 2, new: 1 .. 3 } ]);
+
+		assert_eq! (
+			line_changes (& [ "a", "", "", "b" ], & [ "a", "", "b" ]),
+			vec! [ Change { old: 2 .. 3, new: 2 .. 2 } ]);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,103 @@
+//! Errors which stop a file, or a whole run, from being processed.
+//!
+//! Problems found in the files themselves are [`Finding`](crate::Finding)s,
+//! not errors.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[ derive (Debug) ]
+pub enum Error {
+
+	/// Reading or writing a file or stream failed.
+	Io {
+		action: & 'static str,
+		path: String,
+		source: io::Error,
+	},
+
+	/// The config file is invalid. The message starts with the line number.
+	Config {
+		path: PathBuf,
+		message: String,
+	},
+
+	/// Invalid options, eg an unknown rule.
+	Usage (String),
+
+	/// Running git failed, or it gave unexpected output.
+	Git (String),
+
+	/// A language server client sent an invalid message.
+	Protocol (String),
+
+}
+
+impl Error {
+
+	pub fn io (
+		action: & 'static str,
+		path: impl Into <String>,
+		source: io::Error,
+	) -> Error {
+
+		Error::Io {
+			action,
+			path: path.into (),
+			source,
+		}
+
+	}
+
+	/// Whether this is a problem with the options or configuration, rather
+	/// than something going wrong while running.
+	pub fn is_usage (
+		& self,
+	) -> bool {
+		matches! (self, Error::Config { .. } | Error::Usage (_))
+	}
+
+}
+
+impl fmt::Display for Error {
+
+	fn fmt (
+		& self,
+		formatter: & mut fmt::Formatter,
+	) -> fmt::Result {
+
+		match self {
+
+			Error::Io { action, path, source } =>
+				write! (formatter, "Error {} {}: {}", action, path, source),
+
+			Error::Config { path, message } =>
+				write! (formatter, "Error in config file {}:{}", path.display (), message),
+
+			Error::Usage (message) | Error::Git (message) | Error::Protocol (message) =>
+				write! (formatter, "{}", message),
+
+		}
+
+	}
+
+}
+
+impl error::Error for Error {
+
+	fn source (
+		& self,
+	) -> Option <& (dyn error::Error + 'static)> {
+
+		match self {
+			Error::Io { source, .. } => Some (source),
+			_ => None,
+		}
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
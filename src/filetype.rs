@@ -0,0 +1,152 @@
+//! File type detection.
+//!
+//! File types use the same names as vim, so that they match the `filetype`
+//! modeline option. The file name is checked first, then the contents are
+//! examined for a shebang line or other recognisable header.
+
+use std::path::Path;
+
+pub fn detect (
+	filename: & str,
+	contents: & str,
+) -> Option <& 'static str> {
+
+	from_filename (filename)
+		.or_else (|| from_contents (contents))
+
+}
+
+pub fn from_filename (
+	filename: & str,
+) -> Option <& 'static str> {
+
+	let file_name = Path::new (filename).file_name () ?.to_str () ?;
+
+	if matches! (file_name, "Makefile" | "makefile" | "GNUmakefile") {
+		return Some ("make");
+	}
+
+	if matches! (file_name, "COMMIT_EDITMSG" | "MERGE_MSG" | "TAG_EDITMSG") {
+		return Some ("gitcommit");
+	}
+
+	let extension = file_name.rsplit_once ('.') ?.1;
+
+	Some (match extension {
+		"c" | "h" => "c",
+		"cc" | "cpp" | "cxx" | "hh" | "hpp" => "cpp",
+		"css" => "css",
+		"go" => "go",
+		"html" | "htm" => "html",
+		"java" => "java",
+		"js" => "javascript",
+		"json" => "json",
+		"md" | "markdown" | "mdown" | "mkd" => "markdown",
+		"mk" => "make",
+		"pl" | "pm" => "perl",
+		"py" => "python",
+		"rb" => "ruby",
+		"rs" => "rust",
+		"sh" | "bash" => "sh",
+		"toml" => "toml",
+		"ts" => "typescript",
+		"txt" => "text",
+		"xml" => "xml",
+		"yml" | "yaml" => "yaml",
+		"zsh" => "zsh",
+		_ => return None,
+	})
+
+}
+
+pub fn from_contents (
+	contents: & str,
+) -> Option <& 'static str> {
+
+	let first_line = contents.lines ().next () ?;
+
+	if let Some (shebang) = first_line.strip_prefix ("#!") {
+		return from_shebang (shebang);
+	}
+
+	if first_line.starts_with ("<?xml") {
+		return Some ("xml");
+	}
+
+	if first_line.to_ascii_lowercase ().starts_with ("<!doctype html") {
+		return Some ("html");
+	}
+
+	None
+
+}
+
+fn from_shebang (
+	shebang: & str,
+) -> Option <& 'static str> {
+
+	let mut words = shebang.split_whitespace ();
+	let mut interpreter = Path::new (words.next () ?).file_name () ?.to_str () ?;
+
+	// skip over env and any options given to it
+
+	if interpreter == "env" {
+		interpreter = words.find (|word| ! word.starts_with ('-')) ?;
+	}
+
+	// ignore version numbers, eg python3 or python3.11
+
+	let interpreter = interpreter.trim_end_matches (|character: char|
+		character.is_ascii_digit () || character == '.');
+
+	Some (match interpreter {
+		"ash" | "bash" | "dash" | "ksh" | "sh" => "sh",
+		"make" => "make",
+		"node" | "nodejs" => "javascript",
+		"perl" => "perl",
+		"python" => "python",
+		"ruby" => "ruby",
+		"zsh" => "zsh",
+		_ => return None,
+	})
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn from_filename () {
+
+		assert_eq! (detect ("src/main.rs", ""), Some ("rust"));
+		assert_eq! (detect ("README.markdown", ""), Some ("markdown"));
+		assert_eq! (detect ("dir/Makefile", ""), Some ("make"));
+		assert_eq! (detect (".git/COMMIT_EDITMSG", ""), Some ("gitcommit"));
+		assert_eq! (detect ("archive.tar.gz", ""), None);
+		assert_eq! (detect ("LICENSE", ""), None);
+
+	}
+
+	#[ test ]
+	fn from_contents () {
+
+		assert_eq! (detect ("script", "#!/bin/sh\n"), Some ("sh"));
+		assert_eq! (detect ("script", "#!/usr/bin/env -S python3.11 -u\n"), Some ("python"));
+		assert_eq! (detect ("script", "#! /usr/bin/node\n"), Some ("javascript"));
+		assert_eq! (detect ("script", "#!/usr/bin/env awk\n"), None);
+		assert_eq! (detect ("page", "<!DOCTYPE html>\n"), Some ("html"));
+		assert_eq! (detect ("data", "<?xml version=\"1.0\"?>\n"), Some ("xml"));
+		assert_eq! (detect ("data", ""), None);
+
+	}
+
+	#[ test ]
+	fn filename_takes_precedence () {
+		assert_eq! (detect ("script.rb", "#!/usr/bin/python\n"), Some ("ruby"));
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
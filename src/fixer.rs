@@ -0,0 +1,214 @@
+//! Checking and fixing whole files, using the rules in a [`Registry`].
+
+use std::borrow::Cow;
+use std::io::Read;
+use std::io::Write;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::rules::Registry;
+
+/// Number of problems found in some text, split by whether they can be fixed
+/// automatically. Problems which can't be fixed are errors or warnings,
+/// depending on the rule's severity. Problems which could be fixed but
+/// weren't, eg when only checking, are counted separately.
+#[ derive (Clone, Copy, Debug, Default) ]
+pub struct CheckResult {
+	pub fixable_errors: u64,
+	pub unfixed_errors: u64,
+	pub unfixable_errors: u64,
+	pub warnings: u64,
+}
+
+impl CheckResult {
+
+	pub fn from_findings (
+		findings: & [Finding],
+	) -> CheckResult {
+
+		let count = |predicate: fn (& & Finding) -> bool|
+			findings.iter ().filter (predicate).count () as u64;
+
+		CheckResult {
+			fixable_errors: count (|finding| finding.fixable),
+			unfixed_errors: count (|finding|
+				! finding.fixable && finding.reason.is_none () && ! finding.warning),
+			unfixable_errors: count (|finding|
+				! finding.fixable && finding.reason.is_some () && ! finding.warning),
+			warnings: count (|finding| ! finding.fixable && finding.warning),
+		}
+
+	}
+
+	/// Errors which are left in the text.
+	pub fn remaining_errors (
+		& self,
+	) -> u64 {
+		self.unfixed_errors + self.unfixable_errors
+	}
+
+}
+
+/// A problem found on a particular line, which may or may not have been fixed.
+/// Problems which the rule can't fix have a reason, to tell them apart from
+/// those which could have been fixed but weren't, eg when only checking.
+#[ derive (Clone, Debug) ]
+pub struct Finding {
+	pub line_number: u64,
+	pub column: Option <usize>,
+	pub rule: String,
+	pub message: Cow <'static, str>,
+	pub fixable: bool,
+	pub reason: Option <Cow <'static, str>>,
+	pub warning: bool,
+}
+
+/// Count the problems in some text without fixing them.
+pub fn check_str (
+	config: & Config,
+	contents: & str,
+) -> CheckResult {
+
+	let (_, findings) = fix_str (config, contents);
+
+	CheckResult::from_findings (& findings)
+
+}
+
+/// Fix the problems in some text, returning the fixed text along with a
+/// finding for every problem, whether or not it could be fixed.
+pub fn fix_str (
+	config: & Config,
+	contents: & str,
+) -> (String, Vec <Finding>) {
+
+	Registry::builtin ().run (config, contents)
+
+}
+
+/// Checks and fixes text read from a [`Read`] and written to a [`Write`].
+pub struct Fixer {
+	config: Config,
+	registry: Registry,
+}
+
+impl Fixer {
+
+	pub fn new (
+		config: Config,
+	) -> Fixer {
+		Fixer::with_registry (config, Registry::builtin ())
+	}
+
+	/// Use a different set of rules, eg to add custom rules.
+	pub fn with_registry (
+		config: Config,
+		registry: Registry,
+	) -> Fixer {
+		Fixer { config, registry }
+	}
+
+	pub fn check (
+		& mut self,
+		input: & mut dyn Read,
+	) -> Result <CheckResult, Error> {
+
+		let contents = read_input (input) ?;
+
+		let (_, findings) = self.registry.run (& self.config, & contents);
+
+		Ok (CheckResult::from_findings (& findings))
+
+	}
+
+	pub fn fix (
+		& mut self,
+		input: & mut dyn Read,
+		output: & mut dyn Write,
+	) -> Result <Vec <Finding>, Error> {
+
+		let contents = read_input (input) ?;
+
+		let (fixed_contents, findings) = self.registry.run (& self.config, & contents);
+
+		if let Err (error) = output.write_all (fixed_contents.as_bytes ()) {
+			return Err (Error::io ("writing", "output", error));
+		}
+
+		Ok (findings)
+
+	}
+
+}
+
+fn read_input (
+	input: & mut dyn Read,
+) -> Result <String, Error> {
+
+	let mut contents = String::new ();
+
+	if let Err (error) = input.read_to_string (& mut contents) {
+		return Err (Error::io ("reading", "input", error));
+	}
+
+	Ok (contents)
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn counts_findings () {
+
+		let finding = |fixable: bool, reason: Option <& 'static str>, warning: bool| Finding {
+			line_number: 1,
+			column: None,
+			rule: "rule".to_owned (),
+			message: Cow::Borrowed ("message"),
+			fixable,
+			reason: reason.map (Cow::Borrowed),
+			warning,
+		};
+
+		let result = CheckResult::from_findings (& [
+			finding (true, None, false),
+			finding (true, None, true),
+			finding (false, None, false),
+			finding (false, Some ("reason"), false),
+			finding (false, Some ("reason"), false),
+			finding (false, Some ("reason"), true),
+		]);
+
+		assert_eq! (result.fixable_errors, 2);
+		assert_eq! (result.unfixed_errors, 1);
+		assert_eq! (result.unfixable_errors, 2);
+		assert_eq! (result.warnings, 1);
+		assert_eq! (result.remaining_errors (), 3);
+
+	}
+
+	#[ test ]
+	fn checks_and_fixes_streams () {
+
+		let mut fixer = Fixer::new (Config::default ());
+
+		let result = fixer.check (& mut "a \nb\n".as_bytes ()).unwrap ();
+
+		assert_eq! (result.fixable_errors, 1);
+
+		let mut output = Vec::new ();
+		let findings = fixer.fix (& mut "a \nb\n".as_bytes (), & mut output).unwrap ();
+
+		assert_eq! (output, b"a\nb\n");
+		assert_eq! (findings.len (), 1);
+
+		assert! (fixer.check (& mut & b"\xff"[..]).is_err ());
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
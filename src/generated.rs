@@ -0,0 +1,61 @@
+//! Detection of generated files.
+//!
+//! Code generators such as protoc, bindgen and go generate mark their output
+//! with a comment near the top. Fixing these files only creates changes which
+//! are lost the next time they are generated, so they are skipped by default.
+
+/// Number of lines at the start of a file which are searched for a marker.
+pub const MARKER_LINES: usize = 10;
+
+/// Markers which generators use, which are unlikely to appear in prose about
+/// generated files, unlike eg "automatically generated".
+const MARKERS: & [& str] = & [
+	"@generated",
+	"DO NOT EDIT",
+];
+
+/// Whether a file looks like it was created by a code generator.
+pub fn is_generated (
+	contents: & str,
+) -> bool {
+
+	contents.lines ()
+		.take (MARKER_LINES)
+		.any (|line| MARKERS.iter ().any (|marker| line.contains (marker)))
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn finds_markers () {
+
+		assert! (is_generated ("// Code generated by protoc-gen-go. DO NOT EDIT.\npackage x\n"));
+		assert! (is_generated ("/* automatically generated by rust-bindgen */\n// @generated\n"));
+
+	}
+
+	#[ test ]
+	fn ignores_prose () {
+
+		assert! (! is_generated ("# Generated files\n\nDo not edit files which are generated.\n"));
+		assert! (! is_generated ("This file was automatically generated.\n"));
+
+	}
+
+	#[ test ]
+	fn only_searches_first_lines () {
+
+		let mut contents = "\n".repeat (MARKER_LINES);
+		contents.push_str ("// @generated\n");
+
+		assert! (! is_generated (& contents));
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
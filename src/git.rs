@@ -0,0 +1,192 @@
+//! Finding files to process by asking git.
+//!
+//! This runs the `git` command rather than reading the repository directly,
+//! so it respects the user's git configuration and works with any repository
+//! layout git itself understands. Paths are returned relative to the current
+//! directory, and only files under it are included, apart from changed lines,
+//! which are given for absolute paths so they can match files however they
+//! are named.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::diff;
+use crate::diff::LineRanges;
+use crate::error::Error;
+use crate::paths;
+
+// hash of the empty tree, to compare against before the first commit
+
+const EMPTY_TREE: & str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+fn run_git (
+	args: & [& str],
+) -> Result <String, Error> {
+
+	let output = match Command::new ("git").args (args).output () {
+		Ok (output) => output,
+		Err (error) => return Err (Error::Git (format! ("Error running git: {}", error))),
+	};
+
+	if ! output.status.success () {
+		return Err (Error::Git (format! (
+			"Error running git {}: {}",
+			args.join (" "),
+			String::from_utf8_lossy (& output.stderr).trim ())));
+	}
+
+	match String::from_utf8 (output.stdout) {
+		Ok (stdout) => Ok (stdout),
+		Err (_) => Err (Error::Git (format! ("Invalid output from git {}", args.join (" ")))),
+	}
+
+}
+
+fn split_paths (
+	output: & str,
+) -> Vec <String> {
+
+	// deleted files and submodules are listed by git but can't be processed
+
+	output.split ('\0')
+		.filter (|path| ! path.is_empty () && Path::new (path).is_file ())
+		.map (str::to_owned)
+		.collect ()
+
+}
+
+fn has_head () -> bool {
+	run_git (& ["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok ()
+}
+
+/// Files which git is tracking.
+pub fn tracked_files () -> Result <Vec <String>, Error> {
+
+	Ok (split_paths (& run_git (& ["ls-files", "-z"]) ?))
+
+}
+
+/// Whether the current directory is in a git working tree.
+pub fn is_work_tree () -> bool {
+	run_git (& ["rev-parse", "--is-inside-work-tree"])
+		.is_ok_and (|output| output.trim_end () == "true")
+}
+
+/// Files which git is tracking, along with new files which aren't ignored.
+pub fn unignored_files () -> Result <Vec <String>, Error> {
+
+	let mut paths = split_paths (& run_git (& [
+		"ls-files", "-z", "--cached", "--others", "--exclude-standard",
+	]) ?);
+	paths.sort ();
+	paths.dedup ();
+
+	Ok (paths)
+
+}
+
+/// Files which have been added or changed since the last commit, whether or
+/// not they are staged, including new files which aren't ignored.
+pub fn modified_files () -> Result <Vec <String>, Error> {
+
+	let mut output = if has_head () {
+		run_git (& [
+			"diff", "--name-only", "-z", "--relative", "--diff-filter=ACMR", "HEAD",
+		]) ?
+	} else {
+		run_git (& ["ls-files", "-z"]) ?
+	};
+
+	output.push_str (& run_git (& ["ls-files", "-z", "--others", "--exclude-standard"]) ?);
+
+	let mut paths = split_paths (& output);
+	paths.sort ();
+	paths.dedup ();
+
+	Ok (paths)
+
+}
+
+/// Files which are staged to be committed, ignoring deletions.
+pub fn staged_files () -> Result <Vec <String>, Error> {
+
+	Ok (split_paths (& run_git (& [
+		"diff", "--cached", "--name-only", "-z", "--relative", "--diff-filter=ACMR",
+	]) ?))
+
+}
+
+/// Files with changes in the working tree which aren't staged.
+pub fn unstaged_files () -> Result <Vec <String>, Error> {
+
+	Ok (split_paths (& run_git (& ["diff", "--name-only", "-z", "--relative"]) ?))
+
+}
+
+/// Stage the current contents of some files.
+pub fn add (
+	paths: & [& str],
+) -> Result <(), Error> {
+
+	let mut args = vec! ["add", "--"];
+	args.extend (paths);
+
+	run_git (& args) ?;
+
+	Ok (())
+
+}
+
+/// Directory where git looks for hooks, which may be configured to be
+/// somewhere other than `.git/hooks`.
+pub fn hooks_dir () -> Result <PathBuf, Error> {
+
+	Ok (PathBuf::from (run_git (& ["rev-parse", "--git-path", "hooks"]) ?.trim_end ()))
+
+}
+
+/// Lines which have been added or changed since a commit, defaulting to the
+/// last one, for each file in the working tree, including those outside the
+/// current directory. Every line of new files which aren't ignored counts as
+/// changed, as git doesn't include them in diffs.
+pub fn changed_lines (
+	base: Option <& str>,
+) -> Result <Vec <(PathBuf, LineRanges)>, Error> {
+
+	let base = match base {
+		Some (base) => base,
+		None if has_head () => "HEAD",
+		None => EMPTY_TREE,
+	};
+
+	let output = run_git (& [
+		"-c", "core.quotePath=false",
+		"diff", "--unified=0", "--no-color", "--no-ext-diff",
+		"--src-prefix=a/", "--dst-prefix=b/", base, "--",
+	]) ?;
+
+	let top_level = paths::absolute (Path::new (
+		run_git (& ["rev-parse", "--show-toplevel"]) ?.trim_end ()));
+
+	let mut files = diff::changed_lines (& output)
+		.map_err (|error| Error::Git (format! ("Invalid output from git diff: {}", error))) ?;
+
+	let untracked = run_git (& [
+		"ls-files", "-z", "--others", "--exclude-standard", "--full-name", "--", ":/",
+	]) ?;
+
+	files.extend (
+		untracked.split ('\0')
+			.filter (|path| ! path.is_empty ())
+			.map (|path| (path.to_owned (), LineRanges::all ())));
+
+	Ok (
+		files.into_iter ()
+			.map (|(path, line_ranges)| (paths::normalise (& top_level.join (path)), line_ranges))
+			.filter (|(path, line_ranges)| ! line_ranges.is_empty () && path.is_file ())
+			.collect ())
+
+}
+
+// ex: noet ts=4 filetype=rust
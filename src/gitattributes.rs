@@ -0,0 +1,204 @@
+//! Line ending settings from `.gitattributes` files.
+//!
+//! Git converts line endings itself for files with the `text` or `eol`
+//! attributes, so these take precedence over the configured line ending.
+//! Otherwise the file would be changed to one style here and back again by
+//! git. Only `.gitattributes` files in the repository containing the file and
+//! `.git/info/attributes` are read, not the global attributes file.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::config::LineEnding;
+use crate::glob::Glob;
+
+#[ derive (Clone, Copy, PartialEq, Eq) ]
+enum State {
+	Unspecified,
+	Set,
+	Unset,
+	Value,
+}
+
+struct Attributes {
+	text: State,
+	eol: Option <LineEnding>,
+}
+
+/// The line ending git will use for a file, if it has one. Files which git
+/// treats as binary have their line endings kept as they are.
+pub fn line_ending (
+	filename: & str,
+) -> Option <LineEnding> {
+
+	let path = env::current_dir ().ok () ?.join (filename);
+
+	let mut attributes = Attributes {
+		text: State::Unspecified,
+		eol: None,
+	};
+
+	for (dir, attributes_path) in attributes_files (& path) ? {
+		let contents = match fs::read_to_string (& attributes_path) {
+			Ok (contents) => contents,
+			Err (_) => continue,
+		};
+		let relative_path = match path.strip_prefix (& dir) {
+			Ok (relative_path) => relative_path,
+			Err (_) => continue,
+		};
+		apply_attributes (& mut attributes, & contents, relative_path);
+	}
+
+	if attributes.text == State::Unset {
+		return Some (LineEnding::Keep);
+	}
+
+	attributes.eol
+
+}
+
+/// Attributes files which apply to a path, with the directory their patterns
+/// are relative to, from lowest to highest priority.
+fn attributes_files (
+	path: & Path,
+) -> Option <Vec <(PathBuf, PathBuf)>> {
+
+	let mut dirs = Vec::new ();
+
+	for dir in path.ancestors ().skip (1) {
+
+		dirs.push (dir);
+
+		let git_path = dir.join (".git");
+
+		if ! git_path.exists () {
+			continue;
+		}
+
+		let mut files: Vec <(PathBuf, PathBuf)> = dirs.iter ().rev ()
+			.map (|dir| (dir.to_path_buf (), dir.join (".gitattributes")))
+			.collect ();
+
+		if git_path.is_dir () {
+			files.push ((dir.to_path_buf (), git_path.join ("info").join ("attributes")));
+		}
+
+		return Some (files);
+
+	}
+
+	// not in a git repository
+
+	None
+
+}
+
+fn apply_attributes (
+	attributes: & mut Attributes,
+	contents: & str,
+	relative_path: & Path,
+) {
+
+	let relative_path = match relative_path.to_str () {
+		Some (relative_path) => relative_path.replace ('\\', "/"),
+		None => return,
+	};
+
+	let file_name = relative_path.rsplit ('/').next ().unwrap_or (& relative_path);
+
+	for line in contents.lines () {
+
+		let mut parts = line.split_whitespace ();
+
+		let pattern = match parts.next () {
+			Some (pattern) if ! pattern.starts_with ('#') => pattern,
+			_ => continue,
+		};
+
+		// patterns for directories never match files, and a pattern without a
+		// slash matches the file name in any directory
+
+		if pattern.ends_with ('/') {
+			continue;
+		}
+
+		let (pattern, matched_path) = if pattern.contains ('/') {
+			(pattern.trim_start_matches ('/'), relative_path.as_str ())
+		} else {
+			(pattern, file_name)
+		};
+
+		match Glob::new (pattern) {
+			Ok (glob) if glob.is_match (matched_path) => (),
+			_ => continue,
+		}
+
+		for attribute in parts {
+			match attribute {
+				"text" => attributes.text = State::Set,
+				"-text" | "binary" => attributes.text = State::Unset,
+				"!text" => attributes.text = State::Unspecified,
+				"text=auto" => attributes.text = State::Value,
+				"eol=lf" => attributes.eol = Some (LineEnding::Lf),
+				"eol=crlf" => attributes.eol = Some (LineEnding::Crlf),
+				"-eol" | "!eol" => attributes.eol = None,
+				_ => (),
+			}
+		}
+
+	}
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	fn attributes (contents: & str, relative_path: & str) -> Attributes {
+		let mut attributes = Attributes { text: State::Unspecified, eol: None };
+		apply_attributes (& mut attributes, contents, Path::new (relative_path));
+		attributes
+	}
+
+	#[ test ]
+	fn matches_file_names_anywhere () {
+
+		let contents = "# comment\n*.bat text eol=crlf\n*.png binary\n";
+
+		assert_eq! (attributes (contents, "scripts/build.bat").eol, Some (LineEnding::Crlf));
+		assert! (attributes (contents, "scripts/build.bat").text == State::Set);
+		assert! (attributes (contents, "images/logo.png").text == State::Unset);
+		assert_eq! (attributes (contents, "build.sh").eol, None);
+
+	}
+
+	#[ test ]
+	fn matches_paths_from_the_directory () {
+
+		let contents = "/docs/*.txt eol=lf\nvendor/ -text\n";
+
+		assert_eq! (attributes (contents, "docs/a.txt").eol, Some (LineEnding::Lf));
+		assert_eq! (attributes (contents, "src/docs/a.txt").eol, None);
+		assert! (attributes (contents, "vendor/a.c").text == State::Unspecified);
+
+	}
+
+	#[ test ]
+	fn later_lines_take_precedence () {
+
+		let contents = "* text=auto eol=crlf\n*.sh eol=lf\n*.md -eol !text\n";
+
+		assert_eq! (attributes (contents, "a.sh").eol, Some (LineEnding::Lf));
+		assert! (attributes (contents, "a.sh").text == State::Value);
+		assert_eq! (attributes (contents, "a.md").eol, None);
+		assert! (attributes (contents, "a.md").text == State::Unspecified);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,157 @@
+//! Shell style wildcard patterns, for config file sections.
+//!
+//! Patterns are converted to regexes when they are parsed, so matching a
+//! path doesn't need to look at the filesystem.
+
+use regex::Regex;
+
+/// Shell style wildcard pattern, matched against a file name or path.
+///
+/// `*` and `?` match within a single path component, `**` matches across
+/// components, and `[...]` matches a character class.
+pub struct Glob {
+	pattern: String,
+	regex: Regex,
+}
+
+impl Glob {
+
+	pub fn new (
+		pattern: & str,
+	) -> Result <Glob, String> {
+
+		let mut regex_source = String::from ("^");
+		let mut chars = pattern.chars ().peekable ();
+
+		while let Some (character) = chars.next () {
+
+			match character {
+
+				'*' if chars.peek () == Some (& '*') => {
+					chars.next ();
+					if chars.peek () == Some (& '/') {
+						chars.next ();
+						regex_source.push_str ("(?:.*/)?");
+					} else {
+						regex_source.push_str (".*");
+					}
+				},
+
+				'*' => regex_source.push_str ("[^/]*"),
+				'?' => regex_source.push_str ("[^/]"),
+
+				'[' => {
+					regex_source.push ('[');
+					if chars.peek () == Some (& '!') {
+						chars.next ();
+						regex_source.push ('^');
+					}
+					for class_character in chars.by_ref () {
+						if class_character == ']' { break }
+						if class_character == '\\' { regex_source.push ('\\') }
+						regex_source.push (class_character);
+					}
+					regex_source.push (']');
+				},
+
+				character => regex_source.push_str (
+					& regex::escape (& character.to_string ())),
+
+			}
+
+		}
+
+		regex_source.push ('$');
+
+		let regex = match Regex::new (& regex_source) {
+			Ok (regex) => regex,
+			Err (error) => return Err (format! ("Invalid pattern {}: {}", pattern, error)),
+		};
+
+		Ok (Glob {
+			pattern: pattern.to_owned (),
+			regex,
+		})
+
+	}
+
+	pub fn pattern (
+		& self,
+	) -> & str {
+		& self.pattern
+	}
+
+	pub fn is_match (
+		& self,
+		path: & str,
+	) -> bool {
+		self.regex.is_match (path)
+	}
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	fn is_match (pattern: & str, path: & str) -> bool {
+		Glob::new (pattern).unwrap ().is_match (path)
+	}
+
+	#[ test ]
+	fn star_matches_within_component () {
+
+		assert! (is_match ("*.rs", "main.rs"));
+		assert! (is_match ("*.rs", ".rs"));
+		assert! (! is_match ("*.rs", "src/main.rs"));
+		assert! (! is_match ("*.rs", "main.rsx"));
+		assert! (is_match ("src/*", "src/main.rs"));
+		assert! (! is_match ("src/*", "src/bin/main.rs"));
+
+	}
+
+	#[ test ]
+	fn double_star_matches_across_components () {
+
+		assert! (is_match ("**/*.rs", "main.rs"));
+		assert! (is_match ("**/*.rs", "src/bin/main.rs"));
+		assert! (is_match ("vendor/**", "vendor/a/b"));
+		assert! (! is_match ("vendor/**", "src/vendor/a"));
+		assert! (is_match ("a/**/b", "a/b"));
+		assert! (is_match ("a/**/b", "a/x/y/b"));
+		assert! (! is_match ("a/**/b", "a/xb"));
+
+	}
+
+	#[ test ]
+	fn question_mark_and_classes () {
+
+		assert! (is_match ("?.c", "a.c"));
+		assert! (! is_match ("?.c", "ab.c"));
+		assert! (! is_match ("a?b", "a/b"));
+		assert! (is_match ("[ab].c", "b.c"));
+		assert! (! is_match ("[ab].c", "c.c"));
+		assert! (is_match ("[!ab].c", "c.c"));
+		assert! (! is_match ("[!ab].c", "a.c"));
+		assert! (is_match ("[a-c].c", "b.c"));
+
+	}
+
+	#[ test ]
+	fn special_characters_are_literal () {
+
+		assert! (is_match ("a+b(1).c", "a+b(1).c"));
+		assert! (! is_match ("a.c", "abc"));
+		assert! (is_match ("[\\].c", "\\.c"));
+
+	}
+
+	#[ test ]
+	fn keeps_pattern () {
+		assert_eq! (Glob::new ("src/**").unwrap ().pattern (), "src/**");
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,321 @@
+//! Indentation style detection and correction.
+//!
+//! This looks at the leading whitespace of each line to guess whether a file
+//! is indented with tabs or spaces, and for spaces, how many make up one
+//! level of indentation. It can also rewrite indentation which mixes tabs and
+//! spaces into a consistent style.
+
+use crate::width;
+use crate::width::TabStops;
+
+pub struct IndentStyle {
+	pub tabs: bool,
+	pub size: Option <usize>,
+}
+
+pub fn detect (
+	contents: & str,
+) -> Option <IndentStyle> {
+
+	let mut tab_lines = 0;
+	let mut space_lines = 0;
+	let mut size_counts = [0_u64; 9];
+	let mut previous_spaces = 0;
+
+	for line in contents.lines () {
+
+		if line.trim ().is_empty () {
+			continue;
+		}
+
+		if line.starts_with ('\t') {
+			tab_lines += 1;
+			previous_spaces = 0;
+			continue;
+		}
+
+		let spaces = line.len () - line.trim_start_matches (' ').len ();
+
+		if spaces > 0 {
+			space_lines += 1;
+		}
+
+		// single space changes are usually comment continuation lines or
+		// alignment, so they don't tell us anything about the indent size
+
+		let change = spaces.abs_diff (previous_spaces);
+
+		if (2 .. size_counts.len ()).contains (& change) {
+			size_counts [change] += 1;
+		}
+
+		previous_spaces = spaces;
+
+	}
+
+	if tab_lines == 0 && space_lines == 0 {
+		return None;
+	}
+
+	if tab_lines > space_lines {
+		return Some (IndentStyle { tabs: true, size: None });
+	}
+
+	let size = size_counts.iter ()
+		.enumerate ()
+		.filter (|& (_, & count)| count > 0)
+		.max_by_key (|& (size, & count)| (count, size))
+		.map (|(size, _)| size);
+
+	Some (IndentStyle { tabs: false, size })
+
+}
+
+/// Split a line into its leading spaces and tabs and the rest.
+pub fn split_indent (
+	content: & str,
+) -> (& str, & str) {
+
+	let indent_len =
+		content.len () - content.trim_start_matches ([' ', '\t']).len ();
+
+	content.split_at (indent_len)
+
+}
+
+fn tab_indent (
+	indent_width: usize,
+	tab_stops: & TabStops,
+) -> String {
+
+	let mut column = 0;
+	let mut tabs = 0;
+
+	loop {
+		let next_column = tab_stops.next (column);
+		if next_column > indent_width || next_column == column {
+			break;
+		}
+		column = next_column;
+		tabs += 1;
+	}
+
+	format! (
+		"{}{}",
+		"\t".repeat (tabs),
+		" ".repeat (indent_width - column))
+
+}
+
+/// Round the width of the indentation to the nearest multiple of the indent
+/// size, with halves rounded up, eg after spaces were left over from
+/// converting tabs. Lines which continue a block comment, starting with `*`,
+/// are left alone. Returns the old and new widths along with the new content,
+/// or `None` if the indentation is already a multiple.
+pub fn round_indent (
+	content: & str,
+	indent_size: usize,
+	tabs: bool,
+	tab_stops: & TabStops,
+) -> Option <(usize, usize, String)> {
+
+	let (indent, rest) = split_indent (content);
+
+	if indent_size == 0 || rest.is_empty () || rest.starts_with ('*') {
+		return None;
+	}
+
+	let indent_width = width::line_width (indent, tab_stops);
+	let remainder = indent_width % indent_size;
+
+	if remainder == 0 {
+		return None;
+	}
+
+	let rounded_width = if remainder * 2 >= indent_size {
+		indent_width + indent_size - remainder
+	} else {
+		indent_width - remainder
+	};
+
+	let rounded_indent = if tabs && ! tab_stops.is_zero () {
+		tab_indent (rounded_width, tab_stops)
+	} else {
+		" ".repeat (rounded_width)
+	};
+
+	Some ((indent_width, rounded_width, format! ("{}{}", rounded_indent, rest)))
+
+}
+
+/// Rewrite leading whitespace which mixes tabs and spaces, keeping the same
+/// width. With tabs, any remainder less than a full tab is left as spaces.
+/// Returns `None` if the indentation is already consistent.
+pub fn fix_mixed (
+	content: & str,
+	tabs: bool,
+	tab_stops: & TabStops,
+) -> Option <String> {
+
+	let (indent, rest) = split_indent (content);
+
+	if ! (indent.contains (' ') && indent.contains ('\t')) || tab_stops.is_zero () {
+		return None;
+	}
+
+	let indent_width = width::line_width (indent, tab_stops);
+
+	let fixed_indent = if tabs {
+		tab_indent (indent_width, tab_stops)
+	} else {
+		" ".repeat (indent_width)
+	};
+
+	if fixed_indent == indent {
+		return None;
+	}
+
+	Some (fixed_indent + rest)
+
+}
+
+/// Rewrite leading whitespace to use as many tabs as possible, as for smart
+/// tabs. Returns `None` if there is nothing to change.
+pub fn indent_with_tabs (
+	content: & str,
+	tab_stops: & TabStops,
+) -> Option <String> {
+
+	let (indent, rest) = split_indent (content);
+
+	if tab_stops.is_zero () {
+		return None;
+	}
+
+	let fixed_indent =
+		tab_indent (width::line_width (indent, tab_stops), tab_stops);
+
+	if fixed_indent == indent {
+		return None;
+	}
+
+	Some (fixed_indent + rest)
+
+}
+
+/// Replace tabs after the indentation with spaces, as for smart tabs.
+/// Returns `None` if there are no such tabs.
+pub fn align_with_spaces (
+	content: & str,
+	tab_stops: & TabStops,
+) -> Option <String> {
+
+	let (indent, rest) = split_indent (content);
+
+	if ! rest.contains ('\t') {
+		return None;
+	}
+
+	let indent_width = width::line_width (indent, tab_stops);
+
+	Some (format! (
+		"{}{}",
+		indent,
+		width::expand_tabs (rest, indent_width, tab_stops)))
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn detects_tabs () {
+
+		let style = detect ("a\n\tb\n\t\tc\n  d\n").unwrap ();
+
+		assert! (style.tabs);
+		assert_eq! (style.size, None);
+
+	}
+
+	#[ test ]
+	fn detects_space_size () {
+
+		let style = detect ("a\n    b\n        c\n         * d\n    e\nf\n").unwrap ();
+
+		assert! (! style.tabs);
+		assert_eq! (style.size, Some (4));
+
+		let style = detect ("a\n b\n").unwrap ();
+
+		assert! (! style.tabs);
+		assert_eq! (style.size, None);
+
+		assert! (detect ("a\n\nb\n").is_none ());
+
+	}
+
+	#[ test ]
+	fn splits_indent () {
+
+		assert_eq! (split_indent ("\t  a b"), ("\t  ", "a b"));
+		assert_eq! (split_indent ("a"), ("", "a"));
+		assert_eq! (split_indent (" \t"), (" \t", ""));
+
+	}
+
+	#[ test ]
+	fn rounds_indent () {
+
+		let tab_stops = TabStops::uniform (4);
+
+		assert_eq! (
+			round_indent ("     a", 4, false, & tab_stops),
+			Some ((5, 4, "    a".to_owned ())));
+
+		assert_eq! (
+			round_indent ("      a", 4, false, & tab_stops),
+			Some ((6, 8, "        a".to_owned ())));
+
+		assert_eq! (
+			round_indent ("\t   a", 4, true, & tab_stops),
+			Some ((7, 8, "\t\ta".to_owned ())));
+
+		assert_eq! (round_indent ("    a", 4, false, & tab_stops), None);
+		assert_eq! (round_indent ("     * a", 4, false, & tab_stops), None);
+		assert_eq! (round_indent ("     ", 4, false, & tab_stops), None);
+		assert_eq! (round_indent ("     a", 0, false, & tab_stops), None);
+
+	}
+
+	#[ test ]
+	fn fixes_mixed () {
+
+		let tab_stops = TabStops::uniform (4);
+
+		assert_eq! (fix_mixed ("  \t a", true, & tab_stops), Some ("\t a".to_owned ()));
+		assert_eq! (fix_mixed ("  \t a", false, & tab_stops), Some ("     a".to_owned ()));
+		assert_eq! (fix_mixed ("\t a", true, & tab_stops), None);
+		assert_eq! (fix_mixed ("\ta", false, & tab_stops), None);
+		assert_eq! (fix_mixed (" \ta", true, & TabStops::uniform (0)), None);
+
+	}
+
+	#[ test ]
+	fn smart_tabs () {
+
+		let tab_stops = TabStops::uniform (4);
+
+		assert_eq! (indent_with_tabs ("      a", & tab_stops), Some ("\t  a".to_owned ()));
+		assert_eq! (indent_with_tabs ("\t  a", & tab_stops), None);
+
+		assert_eq! (align_with_spaces ("\ta\tb", & tab_stops), Some ("\ta   b".to_owned ()));
+		assert_eq! (align_with_spaces ("\ta b", & tab_stops), None);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,499 @@
+//! Just enough JSON for the language server protocol.
+
+use std::fmt;
+
+#[ derive (Clone, Debug, PartialEq) ]
+pub enum Json {
+	Null,
+	Bool (bool),
+	Number (f64),
+	String (String),
+	Array (Vec <Json>),
+	Object (Vec <(String, Json)>),
+}
+
+impl Json {
+
+	pub fn parse (
+		source: & str,
+	) -> Result <Json, String> {
+
+		let mut parser = Parser { source, position: 0 };
+
+		let value = parser.parse_value () ?;
+
+		parser.skip_whitespace ();
+
+		if parser.position != source.len () {
+			return Err (format! ("Unexpected data at position {}", parser.position));
+		}
+
+		Ok (value)
+
+	}
+
+	pub fn object (
+		items: impl IntoIterator <Item = (& 'static str, Json)>,
+	) -> Json {
+
+		Json::Object (
+			items.into_iter ()
+				.map (|(key, value)| (key.to_owned (), value))
+				.collect ())
+
+	}
+
+	/// Look up a key in an object, or return null.
+	pub fn get (
+		& self,
+		key: & str,
+	) -> & Json {
+
+		match self {
+			Json::Object (items) => items.iter ()
+				.find (|(item_key, _)| item_key == key)
+				.map_or (& Json::Null, |(_, value)| value),
+			_ => & Json::Null,
+		}
+
+	}
+
+	pub fn as_str (
+		& self,
+	) -> Option <& str> {
+
+		match self {
+			Json::String (value) => Some (value),
+			_ => None,
+		}
+
+	}
+
+	pub fn as_array (
+		& self,
+	) -> & [Json] {
+
+		match self {
+			Json::Array (values) => values,
+			_ => & [],
+		}
+
+	}
+
+}
+
+impl From <& str> for Json {
+
+	fn from (value: & str) -> Json {
+		Json::String (value.to_owned ())
+	}
+
+}
+
+impl From <String> for Json {
+
+	fn from (value: String) -> Json {
+		Json::String (value)
+	}
+
+}
+
+impl From <bool> for Json {
+
+	fn from (value: bool) -> Json {
+		Json::Bool (value)
+	}
+
+}
+
+impl From <u64> for Json {
+
+	fn from (value: u64) -> Json {
+		Json::Number (value as f64)
+	}
+
+}
+
+impl From <Vec <Json>> for Json {
+
+	fn from (values: Vec <Json>) -> Json {
+		Json::Array (values)
+	}
+
+}
+
+impl fmt::Display for Json {
+
+	fn fmt (
+		& self,
+		formatter: & mut fmt::Formatter,
+	) -> fmt::Result {
+
+		match self {
+
+			Json::Null => write! (formatter, "null"),
+			Json::Bool (value) => write! (formatter, "{}", value),
+
+			Json::Number (value) if value.fract () == 0.0 && value.abs () < 1e15 =>
+				write! (formatter, "{}", * value as i64),
+			Json::Number (value) => write! (formatter, "{}", value),
+
+			Json::String (value) => write_string (formatter, value),
+
+			Json::Array (values) => {
+				write! (formatter, "[") ?;
+				for (index, value) in values.iter ().enumerate () {
+					if index > 0 { write! (formatter, ",") ?; }
+					write! (formatter, "{}", value) ?;
+				}
+				write! (formatter, "]")
+			},
+
+			Json::Object (items) => {
+				write! (formatter, "{{") ?;
+				for (index, (key, value)) in items.iter ().enumerate () {
+					if index > 0 { write! (formatter, ",") ?; }
+					write_string (formatter, key) ?;
+					write! (formatter, ":{}", value) ?;
+				}
+				write! (formatter, "}}")
+			},
+
+		}
+
+	}
+
+}
+
+fn write_string (
+	formatter: & mut fmt::Formatter,
+	value: & str,
+) -> fmt::Result {
+
+	write! (formatter, "\"") ?;
+
+	for character in value.chars () {
+		match character {
+			'"' => write! (formatter, "\\\"") ?,
+			'\\' => write! (formatter, "\\\\") ?,
+			'\n' => write! (formatter, "\\n") ?,
+			'\r' => write! (formatter, "\\r") ?,
+			'\t' => write! (formatter, "\\t") ?,
+			character if (character as u32) < 0x20 =>
+				write! (formatter, "\\u{:04x}", character as u32) ?,
+			character => write! (formatter, "{}", character) ?,
+		}
+	}
+
+	write! (formatter, "\"")
+
+}
+
+struct Parser <'a> {
+	source: & 'a str,
+	position: usize,
+}
+
+impl <'a> Parser <'a> {
+
+	fn rest (& self) -> & 'a str {
+		& self.source [self.position .. ]
+	}
+
+	fn skip_whitespace (& mut self) {
+		let rest = self.rest ();
+		self.position += rest.len () - rest.trim_start ().len ();
+	}
+
+	fn error (
+		& self,
+		message: & str,
+	) -> String {
+		format! ("{} at position {}", message, self.position)
+	}
+
+	fn expect (
+		& mut self,
+		token: & str,
+	) -> Result <(), String> {
+
+		if ! self.rest ().starts_with (token) {
+			return Err (self.error (& format! ("Expected {}", token)));
+		}
+
+		self.position += token.len ();
+
+		Ok (())
+
+	}
+
+	fn parse_value (
+		& mut self,
+	) -> Result <Json, String> {
+
+		self.skip_whitespace ();
+
+		match self.rest ().chars ().next () {
+			Some ('n') => self.expect ("null").map (|_| Json::Null),
+			Some ('t') => self.expect ("true").map (|_| Json::Bool (true)),
+			Some ('f') => self.expect ("false").map (|_| Json::Bool (false)),
+			Some ('"') => self.parse_string ().map (Json::String),
+			Some ('[') => self.parse_array (),
+			Some ('{') => self.parse_object (),
+			Some ('-' | '0' ..= '9') => self.parse_number (),
+			_ => Err (self.error ("Expected a value")),
+		}
+
+	}
+
+	fn parse_number (
+		& mut self,
+	) -> Result <Json, String> {
+
+		let rest = self.rest ();
+
+		let length = rest.find (|character: char|
+			! matches! (character, '-' | '+' | '.' | 'e' | 'E' | '0' ..= '9'))
+			.unwrap_or (rest.len ());
+
+		let value = match rest [ .. length].parse::<f64> () {
+			Ok (value) => value,
+			Err (_) => return Err (self.error ("Invalid number")),
+		};
+
+		self.position += length;
+
+		Ok (Json::Number (value))
+
+	}
+
+	fn parse_string (
+		& mut self,
+	) -> Result <String, String> {
+
+		self.expect ("\"") ?;
+
+		let mut value = String::new ();
+		let mut chars = self.rest ().char_indices ();
+
+		while let Some ((index, character)) = chars.next () {
+
+			match character {
+
+				'"' => {
+					self.position += index + 1;
+					return Ok (value);
+				},
+
+				'\\' => match chars.next () {
+					Some ((_, '"')) => value.push ('"'),
+					Some ((_, '\\')) => value.push ('\\'),
+					Some ((_, '/')) => value.push ('/'),
+					Some ((_, 'b')) => value.push ('\u{8}'),
+					Some ((_, 'f')) => value.push ('\u{c}'),
+					Some ((_, 'n')) => value.push ('\n'),
+					Some ((_, 'r')) => value.push ('\r'),
+					Some ((_, 't')) => value.push ('\t'),
+					Some ((_, 'u')) => {
+						let code_unit = parse_hex (& mut chars)
+							.ok_or_else (|| self.error ("Invalid unicode escape")) ?;
+						let code_point = if (0xd800 .. 0xdc00).contains (& code_unit) {
+							// surrogate pair, the second half must follow
+							let low = match (chars.next (), chars.next ()) {
+								(Some ((_, '\\')), Some ((_, 'u'))) => parse_hex (& mut chars),
+								_ => None,
+							}.ok_or_else (|| self.error ("Invalid surrogate pair")) ?;
+							0x10000 + ((code_unit - 0xd800) << 10) + (low.wrapping_sub (0xdc00) & 0x3ff)
+						} else {
+							code_unit
+						};
+						value.push (char::from_u32 (code_point).unwrap_or ('\u{fffd}'));
+					},
+					_ => return Err (self.error ("Invalid escape")),
+				},
+
+				character => value.push (character),
+
+			}
+
+		}
+
+		Err (self.error ("Unterminated string"))
+
+	}
+
+	fn parse_array (
+		& mut self,
+	) -> Result <Json, String> {
+
+		self.expect ("[") ?;
+
+		let mut values = Vec::new ();
+
+		self.skip_whitespace ();
+
+		if self.expect ("]").is_ok () {
+			return Ok (Json::Array (values));
+		}
+
+		loop {
+
+			values.push (self.parse_value () ?);
+
+			self.skip_whitespace ();
+
+			if self.expect ("]").is_ok () {
+				return Ok (Json::Array (values));
+			}
+
+			self.expect (",") ?;
+
+		}
+
+	}
+
+	fn parse_object (
+		& mut self,
+	) -> Result <Json, String> {
+
+		self.expect ("{") ?;
+
+		let mut items = Vec::new ();
+
+		self.skip_whitespace ();
+
+		if self.expect ("}").is_ok () {
+			return Ok (Json::Object (items));
+		}
+
+		loop {
+
+			self.skip_whitespace ();
+
+			let key = self.parse_string () ?;
+
+			self.skip_whitespace ();
+			self.expect (":") ?;
+
+			items.push ((key, self.parse_value () ?));
+
+			self.skip_whitespace ();
+
+			if self.expect ("}").is_ok () {
+				return Ok (Json::Object (items));
+			}
+
+			self.expect (",") ?;
+
+		}
+
+	}
+
+}
+
+fn parse_hex (
+	chars: & mut impl Iterator <Item = (usize, char)>,
+) -> Option <u32> {
+
+	let mut value = 0;
+
+	for _ in 0 .. 4 {
+		value = value * 16 + chars.next () ?.1.to_digit (16) ?;
+	}
+
+	Some (value)
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn parses_values () {
+
+		assert_eq! (
+			Json::parse (r#" { "a": [ 1, -2.5, 1e3 ], "b": { "c": null }, "d": true, "e": false } "#),
+			Ok (Json::Object (vec! [
+				("a".to_owned (), Json::Array (vec! [
+					Json::Number (1.0),
+					Json::Number (-2.5),
+					Json::Number (1000.0),
+				])),
+				("b".to_owned (), Json::Object (vec! [ ("c".to_owned (), Json::Null) ])),
+				("d".to_owned (), Json::Bool (true)),
+				("e".to_owned (), Json::Bool (false)),
+			])));
+
+		assert_eq! (Json::parse ("[]"), Ok (Json::Array (Vec::new ())));
+		assert_eq! (Json::parse ("{}"), Ok (Json::Object (Vec::new ())));
+
+	}
+
+	#[ test ]
+	fn parses_escapes () {
+
+		assert_eq! (
+			Json::parse (r#""\"\\\/\b\f\n\r\té😀""#),
+			Ok (Json::String ("\"\\/\u{8}\u{c}\n\r\té😀".to_owned ())));
+
+		assert_eq! (Json::parse (r#""\x""#), Err ("Invalid escape at position 1".to_owned ()));
+		assert_eq! (
+			Json::parse (r#""\u12""#),
+			Err ("Invalid unicode escape at position 1".to_owned ()));
+		assert_eq! (
+			Json::parse (r#""\ud83d""#),
+			Err ("Invalid surrogate pair at position 1".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn reports_errors () {
+
+		assert_eq! (Json::parse (""), Err ("Expected a value at position 0".to_owned ()));
+		assert_eq! (Json::parse ("nul"), Err ("Expected null at position 0".to_owned ()));
+		assert_eq! (Json::parse ("[1 2]"), Err ("Expected , at position 3".to_owned ()));
+		assert_eq! (Json::parse (r#"{"a" 1}"#), Err ("Expected : at position 5".to_owned ()));
+		assert_eq! (Json::parse (r#""abc"#), Err ("Unterminated string at position 1".to_owned ()));
+		assert_eq! (Json::parse ("1 2"), Err ("Unexpected data at position 2".to_owned ()));
+		assert_eq! (Json::parse ("-"), Err ("Invalid number at position 0".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn formats_values () {
+
+		let value = Json::object ([
+			("id", Json::Number (3.0)),
+			("ratio", Json::Number (0.5)),
+			("text", Json::from ("a\"b\\c\nd\u{1}")),
+			("items", Json::from (vec! [ Json::from (true), Json::Null ])),
+		]);
+
+		let formatted = value.to_string ();
+
+		assert_eq! (
+			formatted,
+			r#"{"id":3,"ratio":0.5,"text":"a\"b\\c\nd\u0001","items":[true,null]}"#);
+
+		assert_eq! (Json::parse (& formatted), Ok (value));
+
+	}
+
+	#[ test ]
+	fn accessors () {
+
+		let value = Json::parse (r#"{ "a": "b", "c": [ 1 ] }"#).unwrap ();
+
+		assert_eq! (value.get ("a").as_str (), Some ("b"));
+		assert_eq! (value.get ("c").as_array (), & [ Json::Number (1.0) ]);
+		assert_eq! (value.get ("missing"), & Json::Null);
+		assert_eq! (value.get ("a").get ("b"), & Json::Null);
+		assert_eq! (value.get ("a").as_array (), & []);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,65 @@
+//! Detect and correct whitespace issues in text files.
+//!
+//! The [`check_str`] and [`fix_str`] functions work on text which is already
+//! in memory, and [`Fixer`] does the same over [`std::io::Read`] and
+//! [`std::io::Write`]. Use [`Config::for_file`] first to pick up settings from
+//! the file's type, a config file and any modeline.
+//!
+//! The checks themselves are implemented as [`Rule`]s, and a [`Registry`]
+//! holds the rules to apply. Custom rules can be added with
+//! [`Fixer::with_registry`].
+//!
+//! Everything which reads files, other than through [`Fixer`], or runs other
+//! programs needs the `fs` feature, which is on by default. Without it, the
+//! checks only depend on the text they are given, for use in a browser or
+//! other sandbox.
+
+#[ cfg (feature = "fs") ]
+pub mod cache;
+pub mod config;
+pub mod config_file;
+pub mod diff;
+pub mod error;
+pub mod filetype;
+pub mod generated;
+#[ cfg (feature = "fs") ]
+pub mod git;
+#[ cfg (feature = "fs") ]
+pub mod gitattributes;
+pub mod glob;
+pub mod indent;
+pub mod log;
+pub mod paths;
+#[ cfg (feature = "fs") ]
+pub mod lsp;
+pub mod report;
+pub mod rules;
+#[ cfg (feature = "fs") ]
+pub mod walk;
+pub mod width;
+#[ cfg (feature = "fs") ]
+pub mod xattr;
+
+mod fixer;
+#[ cfg (feature = "fs") ]
+mod json;
+mod lines;
+mod modeline;
+
+pub use config::Config;
+pub use error::Error;
+pub use fixer::CheckResult;
+pub use fixer::Finding;
+pub use fixer::Fixer;
+pub use fixer::check_str;
+pub use fixer::fix_str;
+pub use rules::Registry;
+pub use rules::Rule;
+
+// the examples in the readme are run as tests
+
+#[ cfg (doctest) ]
+#[ doc = include_str! ("../README.md") ]
+pub struct ReadmeDoctests;
+
+// ex: noet ts=4 filetype=rust
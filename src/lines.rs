@@ -0,0 +1,223 @@
+//! Splitting text into lines, keeping each line's ending.
+
+use crate::config::LineEnding;
+
+#[ derive (Default) ]
+pub struct LineEndingCount {
+	count: u64,
+	first_line: u64,
+}
+
+#[ derive (Default) ]
+pub struct LineEndingStats {
+	unix: LineEndingCount,
+	windows: LineEndingCount,
+	mac: LineEndingCount,
+}
+
+impl LineEndingStats {
+
+	pub fn new (
+		contents: & str,
+	) -> LineEndingStats {
+
+		let mut stats = LineEndingStats::default ();
+
+		for (line_number, line) in Lines::new (contents).enumerate () {
+
+			let line_ending_count = match split_line_ending (line) {
+				(_, "\n") => & mut stats.unix,
+				(_, "\r\n") => & mut stats.windows,
+				(_, "\r") => & mut stats.mac,
+				_ => continue,
+			};
+
+			if line_ending_count.count == 0 {
+				line_ending_count.first_line = line_number as u64;
+			}
+
+			line_ending_count.count += 1;
+
+		}
+
+		stats
+
+	}
+
+	fn entries (
+		& self,
+	) -> [(LineEnding, & 'static str, & LineEndingCount); 3] {
+		[
+			(LineEnding::Lf, "unix", & self.unix),
+			(LineEnding::Crlf, "windows", & self.windows),
+			(LineEnding::Cr, "mac", & self.mac),
+		]
+	}
+
+	pub fn is_mixed (
+		& self,
+	) -> bool {
+
+		self.entries ().iter ()
+			.filter (|(_, _, line_ending_count)| line_ending_count.count > 0)
+			.count () > 1
+
+	}
+
+	pub fn dominant (
+		& self,
+	) -> LineEnding {
+
+		let mut dominant = (LineEnding::Lf, 0);
+
+		for (line_ending, _, line_ending_count) in self.entries () {
+			if line_ending_count.count > dominant.1 {
+				dominant = (line_ending, line_ending_count.count);
+			}
+		}
+
+		dominant.0
+
+	}
+
+	pub fn first_mismatch (
+		& self,
+		line_ending: LineEnding,
+	) -> Option <u64> {
+
+		self.entries ().iter ()
+			.filter (|(other_line_ending, _, line_ending_count)|
+				line_ending_count.count > 0
+					&& other_line_ending.target () != line_ending.target ())
+			.map (|(_, _, line_ending_count)| line_ending_count.first_line)
+			.min ()
+
+	}
+
+	pub fn summary (
+		& self,
+	) -> String {
+
+		self.entries ().iter ()
+			.filter (|(_, _, line_ending_count)| line_ending_count.count > 0)
+			.map (|(_, name, line_ending_count)|
+				format! ("{} {}", line_ending_count.count, name))
+			.collect::<Vec <String>> ()
+			.join (", ")
+
+	}
+
+}
+
+pub struct Lines <'a> {
+	remaining: & 'a str,
+}
+
+impl <'a> Lines <'a> {
+
+	pub fn new (
+		contents: & 'a str,
+	) -> Lines <'a> {
+		Lines {
+			remaining: contents,
+		}
+	}
+
+}
+
+impl <'a> Iterator for Lines <'a> {
+
+	type Item = & 'a str;
+
+	fn next (
+		& mut self,
+	) -> Option <& 'a str> {
+
+		if self.remaining.is_empty () {
+			return None;
+		}
+
+		let line_end = match self.remaining.find (['\r', '\n']) {
+			Some (position) if self.remaining [position .. ].starts_with ("\r\n") =>
+				position + 2,
+			Some (position) =>
+				position + 1,
+			None =>
+				self.remaining.len (),
+		};
+
+		let (line, remaining) = self.remaining.split_at (line_end);
+		self.remaining = remaining;
+
+		Some (line)
+
+	}
+
+}
+
+pub fn split_line_ending (
+	line: & str,
+) -> (& str, & str) {
+
+	if let Some (content) = line.strip_suffix ("\r\n") {
+		(content, "\r\n")
+	} else if let Some (content) = line.strip_suffix ('\n') {
+		(content, "\n")
+	} else if let Some (content) = line.strip_suffix ('\r') {
+		(content, "\r")
+	} else {
+		(line, "")
+	}
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn keeps_line_endings () {
+
+		let lines: Vec <& str> = Lines::new ("a\nb\r\nc\rd").collect ();
+
+		assert_eq! (lines, vec! [ "a\n", "b\r\n", "c\r", "d" ]);
+		assert_eq! (Lines::new ("\n\n").count (), 2);
+		assert_eq! (Lines::new ("").count (), 0);
+
+	}
+
+	#[ test ]
+	fn splits_line_endings () {
+
+		assert_eq! (split_line_ending ("a\r\n"), ("a", "\r\n"));
+		assert_eq! (split_line_ending ("a\n"), ("a", "\n"));
+		assert_eq! (split_line_ending ("a\r"), ("a", "\r"));
+		assert_eq! (split_line_ending ("a"), ("a", ""));
+
+	}
+
+	#[ test ]
+	fn counts_line_endings () {
+
+		let stats = LineEndingStats::new ("a\r\nb\nc\r\nd\re");
+
+		assert! (stats.is_mixed ());
+		assert_eq! (stats.dominant (), LineEnding::Crlf);
+		assert_eq! (stats.first_mismatch (LineEnding::Crlf), Some (1));
+		assert_eq! (stats.first_mismatch (LineEnding::Lf), Some (0));
+		assert_eq! (stats.summary (), "1 unix, 2 windows, 1 mac");
+
+		let stats = LineEndingStats::new ("a\nb\n");
+
+		assert! (! stats.is_mixed ());
+		assert_eq! (stats.dominant (), LineEnding::Lf);
+		assert_eq! (stats.first_mismatch (LineEnding::Lf), None);
+
+		assert_eq! (LineEndingStats::new ("a").dominant (), LineEnding::Lf);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
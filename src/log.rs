@@ -0,0 +1,147 @@
+//! Logging to stderr, to see why a file was or wasn't changed, such as where
+//! its settings came from and why it was skipped.
+//!
+//! Each message is a level followed by `key=value` fields. Nothing is logged
+//! until a level is set, normally from `--log-level` or the `RUST_LOG`
+//! variable.
+
+use std::fmt;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+
+use crate::config::Choice;
+
+static LEVEL: AtomicU8 = AtomicU8::new (Level::Off as u8);
+
+#[ derive (Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum Level {
+	Off,
+	Error,
+	Warn,
+	Info,
+	Debug,
+	Trace,
+}
+
+impl Choice for Level {
+	const NAMES: & 'static [(& 'static str, Level)] = & [
+		("off", Level::Off),
+		("error", Level::Error),
+		("warn", Level::Warn),
+		("info", Level::Info),
+		("debug", Level::Debug),
+		("trace", Level::Trace),
+	];
+}
+
+impl Level {
+
+	/// The level from a filter in the form of `RUST_LOG`, a list of levels or
+	/// `target=level` directives separated by commas. Only directives without
+	/// a target, or for this crate, are used, and later ones take precedence.
+	pub fn from_filter (
+		filter: & str,
+	) -> Option <Level> {
+
+		let parse = |name: & str| Level::from_name (name.trim (), true).ok ();
+
+		let is_this_crate = |target: & str| {
+			let target = target.trim ();
+			target == "fix_whitespace" || target.starts_with ("fix_whitespace::")
+		};
+
+		filter.split (',')
+			.filter_map (|directive| match directive.split_once ('=') {
+				Some ((target, name)) if is_this_crate (target) => parse (name),
+				Some (_) => None,
+				None if is_this_crate (directive) => Some (Level::Trace),
+				None => parse (directive),
+			})
+			.next_back ()
+
+	}
+
+	fn name (
+		self,
+	) -> & 'static str {
+
+		match self {
+			Level::Off => "OFF",
+			Level::Error => "ERROR",
+			Level::Warn => "WARN",
+			Level::Info => "INFO",
+			Level::Debug => "DEBUG",
+			Level::Trace => "TRACE",
+		}
+
+	}
+
+}
+
+pub fn set_level (
+	level: Level,
+) {
+	LEVEL.store (level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled (
+	level: Level,
+) -> bool {
+	level != Level::Off && level as u8 <= LEVEL.load (Ordering::Relaxed)
+}
+
+/// Write a message if its level is enabled. The fields are only formatted
+/// when they are written.
+pub fn log (
+	level: Level,
+	fields: fmt::Arguments,
+) {
+
+	if enabled (level) {
+		eprintln! ("{:5} {}", level.name (), fields);
+	}
+
+}
+
+pub fn info (
+	fields: fmt::Arguments,
+) {
+	log (Level::Info, fields);
+}
+
+pub fn debug (
+	fields: fmt::Arguments,
+) {
+	log (Level::Debug, fields);
+}
+
+pub fn trace (
+	fields: fmt::Arguments,
+) {
+	log (Level::Trace, fields);
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn levels_from_filter () {
+
+		assert_eq! (Level::from_filter ("debug"), Some (Level::Debug));
+		assert_eq! (Level::from_filter ("INFO"), Some (Level::Info));
+		assert_eq! (Level::from_filter ("warn,trace"), Some (Level::Trace));
+		assert_eq! (Level::from_filter ("fix_whitespace"), Some (Level::Trace));
+		assert_eq! (Level::from_filter ("info, fix_whitespace::rules = debug"), Some (Level::Debug));
+		assert_eq! (Level::from_filter ("info,other=trace"), Some (Level::Info));
+		assert_eq! (Level::from_filter ("other=trace"), None);
+		assert_eq! (Level::from_filter ("verbose"), None);
+		assert_eq! (Level::from_filter (""), None);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,581 @@
+//! A minimal language server, which publishes findings as diagnostics and
+//! fixes documents through formatting requests and code actions.
+//!
+//! Only full document synchronisation is supported. Messages are read from
+//! one stream and written to another, normally stdin and stdout.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::config::Config;
+use crate::config_file::ConfigFile;
+use crate::error::Error;
+use crate::fixer::Finding;
+use crate::generated;
+use crate::json::Json;
+use crate::lines;
+use crate::lines::Lines;
+use crate::rules::Registry;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const SEVERITY_ERROR: u64 = 1;
+const SEVERITY_WARNING: u64 = 2;
+const SYNC_FULL: u64 = 1;
+
+pub struct Server {
+	config: Config,
+	config_file: Option <ConfigFile>,
+	registry: Registry,
+	documents: HashMap <String, String>,
+}
+
+impl Server {
+
+	pub fn new (
+		config: Config,
+		config_file: Option <ConfigFile>,
+		registry: Registry,
+	) -> Server {
+
+		Server {
+			config,
+			config_file,
+			registry,
+			documents: HashMap::new (),
+		}
+
+	}
+
+	/// Handle messages until the client asks the server to exit, or closes
+	/// the input. Messages which can't be parsed are answered with an error,
+	/// but only a broken stream stops the server.
+	pub fn run (
+		& mut self,
+		input: & mut dyn BufRead,
+		output: & mut dyn Write,
+	) -> Result <(), Error> {
+
+		while let Some (content) = read_message (input) ? {
+
+			let message = match parse_message (& content) {
+				Ok (message) => message,
+				Err (error) => {
+					write_message (output, & error_reply (& Json::Null, PARSE_ERROR, error)) ?;
+					continue;
+				},
+			};
+
+			let method = message.get ("method").as_str ().unwrap_or ("");
+
+			if method == "exit" {
+				return Ok (());
+			}
+
+			for reply in self.handle (method, & message) {
+				write_message (output, & reply) ?;
+			}
+
+		}
+
+		Ok (())
+
+	}
+
+	fn handle (
+		& mut self,
+		method: & str,
+		message: & Json,
+	) -> Vec <Json> {
+
+		let params = message.get ("params");
+		let id = message.get ("id");
+		let uri = params.get ("textDocument").get ("uri").as_str ().unwrap_or ("").to_owned ();
+
+		let result = match method {
+
+			"initialize" => Json::object ([
+				("capabilities", Json::object ([
+					("textDocumentSync", SYNC_FULL.into ()),
+					("documentFormattingProvider", true.into ()),
+					("codeActionProvider", true.into ()),
+				])),
+				("serverInfo", Json::object ([
+					("name", "fix-whitespace".into ()),
+					("version", env! ("CARGO_PKG_VERSION").into ()),
+				])),
+			]),
+
+			"shutdown" => Json::Null,
+
+			"textDocument/didOpen" => {
+				let text = params.get ("textDocument").get ("text").as_str ().unwrap_or ("");
+				self.documents.insert (uri.clone (), text.to_owned ());
+				return vec! [ self.diagnostics (& uri) ];
+			},
+
+			"textDocument/didChange" => {
+				let changes = params.get ("contentChanges").as_array ();
+				if let Some (text) = changes.last ().and_then (|change| change.get ("text").as_str ()) {
+					self.documents.insert (uri.clone (), text.to_owned ());
+				}
+				return vec! [ self.diagnostics (& uri) ];
+			},
+
+			"textDocument/didClose" => {
+				self.documents.remove (& uri);
+				return vec! [ publish_diagnostics (& uri, Vec::new ()) ];
+			},
+
+			"textDocument/formatting" => self.fix_edits (& uri).into (),
+
+			"textDocument/codeAction" => {
+				let edits = self.fix_edits (& uri);
+				if edits.is_empty () {
+					Vec::new ().into ()
+				} else {
+					vec! [ Json::object ([
+						("title", "Fix whitespace problems".into ()),
+						("kind", "source.fixAll".into ()),
+						("edit", Json::object ([
+							("changes", Json::Object (vec! [ (uri, edits.into ()) ])),
+						])),
+					]) ].into ()
+				}
+			},
+
+			// notifications which aren't needed can be ignored
+
+			_ if * id == Json::Null => return Vec::new (),
+
+			_ => return vec! [
+				error_reply (id, METHOD_NOT_FOUND, format! ("Method not found: {}", method)),
+			],
+
+		};
+
+		vec! [ Json::object ([
+			("jsonrpc", "2.0".into ()),
+			("id", id.clone ()),
+			("result", result),
+		]) ]
+
+	}
+
+	fn run_rules (
+		& mut self,
+		uri: & str,
+	) -> Option <(& str, String, Vec <Finding>)> {
+
+		let contents = self.documents.get (uri) ?;
+
+		let config = self.config.for_file (
+			self.config_file.as_ref (),
+			& uri_to_path (uri),
+			contents);
+
+		if ! config.include_generated && generated::is_generated (contents) {
+			return None;
+		}
+
+		let (fixed_contents, findings) = self.registry.run (& config, contents);
+
+		Some ((contents, fixed_contents, findings))
+
+	}
+
+	fn diagnostics (
+		& mut self,
+		uri: & str,
+	) -> Json {
+
+		let (contents, _, findings) = match self.run_rules (uri) {
+			Some (result) => result,
+			None => return publish_diagnostics (uri, Vec::new ()),
+		};
+
+		let source_lines: Vec <& str> =
+			Lines::new (contents)
+				.map (|line| lines::split_line_ending (line).0)
+				.collect ();
+
+		// problems with a column are highlighted from there to the end of the
+		// line, and others across the whole line
+
+		let diagnostics = findings.iter ()
+			.map (|finding| {
+				let line = finding.line_number - 1;
+				let content = source_lines.get (line as usize).copied ().unwrap_or ("");
+				let start = finding.column
+					.and_then (|column| content.char_indices ().nth (column - 1))
+					.map_or (0, |(index, _)| utf16_len (& content [ .. index]));
+				Json::object ([
+					("range", range ((line, start), (line, utf16_len (content)))),
+					("severity", if finding.warning {
+						SEVERITY_WARNING
+					} else {
+						SEVERITY_ERROR
+					}.into ()),
+					("source", "fix-whitespace".into ()),
+					("code", finding.rule.as_str ().into ()),
+					("message", finding.message.as_ref ().into ()),
+				])
+			})
+			.collect ();
+
+		publish_diagnostics (uri, diagnostics)
+
+	}
+
+	/// Edits which fix the whole document, or none if nothing can be fixed.
+	fn fix_edits (
+		& mut self,
+		uri: & str,
+	) -> Vec <Json> {
+
+		let (contents, fixed_contents, _) = match self.run_rules (uri) {
+			Some (result) => result,
+			None => return Vec::new (),
+		};
+
+		if fixed_contents == contents {
+			return Vec::new ();
+		}
+
+		vec! [ Json::object ([
+			("range", range ((0, 0), end_position (contents))),
+			("newText", fixed_contents.into ()),
+		]) ]
+
+	}
+
+}
+
+fn publish_diagnostics (
+	uri: & str,
+	diagnostics: Vec <Json>,
+) -> Json {
+
+	Json::object ([
+		("jsonrpc", "2.0".into ()),
+		("method", "textDocument/publishDiagnostics".into ()),
+		("params", Json::object ([
+			("uri", uri.into ()),
+			("diagnostics", diagnostics.into ()),
+		])),
+	])
+
+}
+
+fn error_reply (
+	id: & Json,
+	code: i64,
+	message: String,
+) -> Json {
+
+	Json::object ([
+		("jsonrpc", "2.0".into ()),
+		("id", id.clone ()),
+		("error", Json::object ([
+			("code", Json::Number (code as f64)),
+			("message", message.into ()),
+		])),
+	])
+
+}
+
+fn range (
+	start: (u64, u64),
+	end: (u64, u64),
+) -> Json {
+
+	let position = |(line, character): (u64, u64)| Json::object ([
+		("line", line.into ()),
+		("character", character.into ()),
+	]);
+
+	Json::object ([
+		("start", position (start)),
+		("end", position (end)),
+	])
+
+}
+
+/// Positions are measured in UTF-16 code units, as in the protocol.
+fn utf16_len (
+	content: & str,
+) -> u64 {
+	content.chars ().map (|character| character.len_utf16 () as u64).sum ()
+}
+
+fn end_position (
+	contents: & str,
+) -> (u64, u64) {
+
+	let mut line = 0;
+	let mut character = 0;
+
+	for source_line in Lines::new (contents) {
+		let (content, ending) = lines::split_line_ending (source_line);
+		if ending.is_empty () {
+			character = utf16_len (content);
+		} else {
+			line += 1;
+		}
+	}
+
+	(line, character)
+
+}
+
+/// Convert a `file:` URI to a path, so file types and config sections can be
+/// matched. Other URIs are used as they are.
+fn uri_to_path (
+	uri: & str,
+) -> String {
+
+	let path = match uri.strip_prefix ("file://") {
+		Some (path) => path,
+		None => return uri.to_owned (),
+	};
+
+	let mut bytes = Vec::new ();
+	let mut remaining = path.as_bytes ();
+
+	while let Some ((& byte, rest)) = remaining.split_first () {
+		let decoded = if byte == b'%' && rest.len () >= 2 {
+			std::str::from_utf8 (& rest [ .. 2]).ok ()
+				.and_then (|hex| u8::from_str_radix (hex, 16).ok ())
+		} else {
+			None
+		};
+		match decoded {
+			Some (decoded) => {
+				bytes.push (decoded);
+				remaining = & rest [2 .. ];
+			},
+			None => {
+				bytes.push (byte);
+				remaining = rest;
+			},
+		}
+	}
+
+	String::from_utf8_lossy (& bytes).into_owned ()
+
+}
+
+/// Read the content of the next message, or `None` at the end of the input.
+fn read_message (
+	input: & mut dyn BufRead,
+) -> Result <Option <Vec <u8>>, Error> {
+
+	let mut content_length = None;
+
+	loop {
+
+		let mut header = String::new ();
+
+		match input.read_line (& mut header) {
+			Ok (0) => return Ok (None),
+			Ok (_) => (),
+			Err (error) => return Err (Error::io ("reading", "message", error)),
+		}
+
+		let header = header.trim_end ();
+
+		if header.is_empty () {
+			break;
+		}
+
+		if let Some ((name, value)) = header.split_once (':') {
+			if name.eq_ignore_ascii_case ("content-length") {
+				content_length = value.trim ().parse::<usize> ().ok ();
+			}
+		}
+
+	}
+
+	let content_length = match content_length {
+		Some (content_length) => content_length,
+		None => return Err (Error::Protocol ("Message has no Content-Length header".to_owned ())),
+	};
+
+	let mut content = vec! [0; content_length];
+
+	if let Err (error) = input.read_exact (& mut content) {
+		return Err (Error::io ("reading", "message", error));
+	}
+
+	Ok (Some (content))
+
+}
+
+fn parse_message (
+	content: & [u8],
+) -> Result <Json, String> {
+
+	let content = match std::str::from_utf8 (content) {
+		Ok (content) => content,
+		Err (_) => return Err ("Message is not valid UTF-8".to_owned ()),
+	};
+
+	Json::parse (content)
+		.map_err (|error| format! ("Invalid message: {}", error))
+
+}
+
+fn write_message (
+	output: & mut dyn Write,
+	message: & Json,
+) -> Result <(), Error> {
+
+	let content = message.to_string ();
+
+	write! (output, "Content-Length: {}\r\n\r\n{}", content.len (), content)
+		.and_then (|_| output.flush ())
+		.map_err (|error| Error::io ("writing", "message", error))
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	fn message (content: & Json) -> Vec <u8> {
+		let mut output = Vec::new ();
+		write_message (& mut output, content).unwrap ();
+		output
+	}
+
+	fn replies (output: & [u8]) -> Vec <Json> {
+		let mut output = output;
+		let mut replies = Vec::new ();
+		while let Some (reply) = read_message (& mut output).unwrap () {
+			replies.push (parse_message (& reply).unwrap ());
+		}
+		replies
+	}
+
+	#[ test ]
+	fn converts_uris () {
+
+		assert_eq! (uri_to_path ("file:///home/a%20b/c%C3%A9.rs"), "/home/a b/cé.rs");
+		assert_eq! (uri_to_path ("file:///a%2"), "/a%2");
+		assert_eq! (uri_to_path ("untitled:Untitled-1"), "untitled:Untitled-1");
+
+	}
+
+	#[ test ]
+	fn measures_utf16 () {
+
+		assert_eq! (utf16_len ("aé😀"), 4);
+		assert_eq! (end_position (""), (0, 0));
+		assert_eq! (end_position ("a\r\nb😀"), (1, 3));
+		assert_eq! (end_position ("a\nb\n"), (2, 0));
+
+	}
+
+	#[ test ]
+	fn reads_and_writes_messages () {
+
+		let content = Json::object ([ ("id", 1_u64.into ()), ("method", "é".into ()) ]);
+		let bytes = message (& content);
+
+		assert! (bytes.starts_with (b"Content-Length: 22\r\n\r\n"));
+		assert_eq! (replies (& bytes), vec! [ content ]);
+		assert_eq! (read_message (& mut & b""[..]).unwrap (), None);
+		assert! (read_message (& mut & b"Other: 1\r\n\r\n{}"[..]).is_err ());
+		assert! (parse_message (b"{").is_err ());
+		assert! (parse_message (b"\xff").is_err ());
+
+	}
+
+	#[ test ]
+	fn publishes_diagnostics_and_fixes () {
+
+		let open = Json::object ([
+			("method", "textDocument/didOpen".into ()),
+			("params", Json::object ([
+				("textDocument", Json::object ([
+					("uri", "file:///a.txt".into ()),
+					("text", "é \nb\n".into ()),
+				])),
+			])),
+		]);
+
+		let format = Json::object ([
+			("id", 2_u64.into ()),
+			("method", "textDocument/formatting".into ()),
+			("params", Json::object ([
+				("textDocument", Json::object ([ ("uri", "file:///a.txt".into ()) ])),
+			])),
+		]);
+
+		let unknown = Json::object ([ ("id", 3_u64.into ()), ("method", "unknown".into ()) ]);
+		let exit = Json::object ([ ("method", "exit".into ()) ]);
+
+		let input: Vec <u8> = [ open, format, unknown, exit ].iter ().flat_map (message).collect ();
+		let mut output = Vec::new ();
+
+		Server::new (Config::default (), None, Registry::builtin ())
+			.run (& mut input.as_slice (), & mut output)
+			.unwrap ();
+
+		let replies = replies (& output);
+
+		assert_eq! (replies.len (), 3);
+
+		let diagnostics = replies [0].get ("params").get ("diagnostics").as_array ();
+
+		assert_eq! (diagnostics.len (), 1);
+		assert_eq! (diagnostics [0].get ("code").as_str (), Some ("trailing-whitespace"));
+		assert_eq! (diagnostics [0].get ("range"), & range ((0, 1), (0, 2)));
+		assert_eq! (diagnostics [0].get ("severity"), & Json::Number (SEVERITY_ERROR as f64));
+
+		let edits = replies [1].get ("result").as_array ();
+
+		assert_eq! (edits.len (), 1);
+		assert_eq! (edits [0].get ("newText").as_str (), Some ("é\nb\n"));
+		assert_eq! (edits [0].get ("range"), & range ((0, 0), (2, 0)));
+
+		assert_eq! (
+			replies [2].get ("error").get ("code"),
+			& Json::Number (METHOD_NOT_FOUND as f64));
+
+	}
+
+	#[ test ]
+	fn answers_malformed_messages () {
+
+		let format = Json::object ([
+			("id", 1_u64.into ()),
+			("method", "textDocument/formatting".into ()),
+			("params", Json::object ([
+				("textDocument", Json::object ([ ("uri", "file:///a.txt".into ()) ])),
+			])),
+		]);
+
+		let mut input = b"Content-Length: 1\r\n\r\n{".to_vec ();
+		input.extend (message (& format));
+
+		let mut output = Vec::new ();
+
+		Server::new (Config::default (), None, Registry::builtin ())
+			.run (& mut input.as_slice (), & mut output)
+			.unwrap ();
+
+		let replies = replies (& output);
+
+		assert_eq! (replies.len (), 2);
+		assert_eq! (replies [0].get ("id"), & Json::Null);
+		assert_eq! (replies [0].get ("error").get ("code"), & Json::Number (PARSE_ERROR as f64));
+		assert_eq! (replies [1].get ("id"), & Json::Number (1.0));
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
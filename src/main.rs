@@ -1,18 +1,47 @@
-use clap::Parser as _;
-use regex::Regex;
-use std::borrow::Cow;
+use clap::ArgMatches;
+use clap::CommandFactory as _;
+use clap::FromArgMatches as _;
+use clap::ValueSource;
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
+use std::fs::OpenOptions;
+use std::env;
 use std::io;
-use std::io::BufRead;
-use std::io::BufReader;
+use std::io::IsTerminal as _;
 use std::io::Read;
-use std::io::Seek;
-use std::io::SeekFrom;
 use std::io::Write;
-use std::iter;
-use std::ops::AddAssign;
-use std::rc::Rc;
+use std::mem;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use fix_whitespace::CheckResult;
+use fix_whitespace::Config;
+use fix_whitespace::Error;
+use fix_whitespace::Finding;
+use fix_whitespace::Registry;
+use fix_whitespace::cache;
+use fix_whitespace::cache::Cache;
+use fix_whitespace::config_file::ConfigFile;
+use fix_whitespace::diff::LineRanges;
+use fix_whitespace::generated;
+use fix_whitespace::git;
+use fix_whitespace::log;
+use fix_whitespace::log::Level;
+use fix_whitespace::paths;
+use fix_whitespace::lsp;
+use fix_whitespace::report::Format;
+use fix_whitespace::report::Reporter;
+use fix_whitespace::report::Statistics;
+use fix_whitespace::report::Verbosity;
+use fix_whitespace::report::plural;
+use fix_whitespace::walk;
+use fix_whitespace::xattr;
 
 #[ derive (clap::Parser) ]
 pub struct Args {
@@ -20,521 +49,1546 @@ pub struct Args {
 	#[ clap (flatten) ]
 	config: Config,
 
-	#[ clap (help = "List of files to process") ]
+	#[ clap (long = "config", env = "FIX_WHITESPACE_CONFIG") ]
+	#[ clap (help = "Configuration file to use instead of searching for .fix-whitespace.toml") ]
+	config_file: Option <String>,
+
+	#[ clap (long, conflicts_with_all = & ["git-modified", "git-tracked"]) ]
+	#[ clap (help = "Run as a git pre-commit hook, fixing and restaging staged files") ]
+	hook: bool,
+
+	#[ clap (long, conflicts_with = "git-tracked") ]
+	#[ clap (help = "Process files which have changed since the last commit, according to git") ]
+	git_modified: bool,
+
+	#[ clap (long) ]
+	#[ clap (help = "Process all files which are tracked by git") ]
+	git_tracked: bool,
+
+	#[ clap (long, value_name = "BASE", min_values = 0, require_equals = true) ]
+	#[ clap (help = "Only fix lines changed since a git commit, defaulting to HEAD") ]
+	changed_lines: Option <Option <String>>,
+
+	#[ clap (long, value_name = "PATH", conflicts_with = "stdin-filepath") ]
+	#[ clap (help = "Read the list of files to process from a file, or - for stdin") ]
+	files_from: Option <String>,
+
+	#[ clap (short = '0', long, requires = "files-from") ]
+	#[ clap (help = "Names in the --files-from list are separated by NUL instead of newline") ]
+	null: bool,
+
+	#[ clap (long, value_name = "EXTENSIONS", use_value_delimiter = true) ]
+	#[ clap (help = "Only process files with these extensions, separated by commas") ]
+	ext: Vec <String>,
+
+	#[ clap (long, value_name = "PATH") ]
+	#[ clap (help = "Fix stdin to stdout, with settings for a file at this path") ]
+	stdin_filepath: Option <String>,
+
+	#[ clap (short = 'l', long) ]
+	#[ clap (conflicts_with_all = & ["quiet", "verbose", "stats", "hook", "stdin-filepath"]) ]
+	#[ clap (help = "Only list files with problems, without fixing them") ]
+	files_with_errors: bool,
+
+	#[ clap (long, value_name = "SUFFIX", min_values = 0, require_equals = true) ]
+	#[ clap (conflicts_with = "files-with-errors") ]
+	#[ clap (help = "Keep the original of each fixed file, with this suffix, defaulting to .orig") ]
+	backup: Option <Option <String>>,
+
+	#[ clap (long) ]
+	#[ clap (help = "Fix read-only files, overwrite existing backups, and replace existing hooks") ]
+	force: bool,
+
+	#[ clap (long, overrides_with = "no-follow-symlinks") ]
+	#[ clap (help = "Fix the files which symlinks point to, and search symlinked directories") ]
+	follow_symlinks: bool,
+
+	#[ clap (long, overrides_with = "follow-symlinks") ]
+	#[ clap (help = "Leave files behind symlinks unchanged, which is the default") ]
+	no_follow_symlinks: bool,
+
+	#[ clap (short, long) ]
+	#[ clap (help = "Fix any number of files when none are given, without asking") ]
+	yes: bool,
+
+	#[ clap (long, value_name = "N", default_value = "20") ]
+	#[ clap (env = "FIX_WHITESPACE_CONFIRM_LIMIT") ]
+	#[ clap (help = "Ask before fixing more than this many files when none are given") ]
+	confirm_limit: usize,
+
+	#[ clap (long) ]
+	#[ clap (help = "Fix files with hard links, replacing them with separate files") ]
+	break_hardlinks: bool,
+
+	#[ clap (long, value_name = "BYTES", default_value = "10485760") ]
+	#[ clap (overrides_with = "no-max-filesize") ]
+	#[ clap (help = "Skip files larger than this") ]
+	max_filesize: u64,
+
+	#[ clap (long, overrides_with = "max-filesize") ]
+	#[ clap (help = "Process files of any size") ]
+	no_max_filesize: bool,
+
+	#[ clap (long) ]
+	#[ clap (help = "Keep the modification time of fixed files") ]
+	preserve_mtime: bool,
+
+	#[ clap (long, value_name = "DIRECTORY") ]
+	#[ clap (conflicts_with_all = & ["files-with-errors", "backup", "hook", "stdin-filepath", "cache"]) ]
+	#[ clap (help = "Write fixed copies of files under this directory, leaving the originals") ]
+	output_dir: Option <String>,
+
+	#[ clap (long, conflicts_with = "changed-lines") ]
+	#[ clap (help = "Remember which files are clean, and skip them if they haven't changed") ]
+	cache: bool,
+
+	#[ clap (long, value_name = "PATH", default_value = cache::DEFAULT_LOCATION) ]
+	#[ clap (help = "File to keep the cache in") ]
+	cache_location: String,
+
+	#[ clap (short, long) ]
+	#[ clap (conflicts_with_all = & ["files-with-errors", "hook", "stdin-filepath"]) ]
+	#[ clap (help = "Show the changes to each file, and ask before fixing it") ]
+	interactive: bool,
+
+	#[ clap (long) ]
+	#[ clap (help = "Stop at the first file with problems which weren't fixed, or an error") ]
+	fail_fast: bool,
+
+	#[ clap (long, value_name = "N") ]
+	#[ clap (help = "Stop after this many problems which weren't fixed, or errors") ]
+	max_errors: Option <u64>,
+
+	#[ clap (short, long, conflicts_with = "verbose") ]
+	#[ clap (help = "Only report errors") ]
+	quiet: bool,
+
+	#[ clap (short, long) ]
+	#[ clap (help = "Report every line with problems, including those fixed") ]
+	verbose: bool,
+
+	#[ clap (long, arg_enum, default_value = "auto", alias = "colour") ]
+	#[ clap (env = "FIX_WHITESPACE_COLOR") ]
+	#[ clap (help = "Use colours in the output") ]
+	color: ColourChoice,
+
+	#[ clap (long, arg_enum, default_value = "text") ]
+	#[ clap (env = "FIX_WHITESPACE_FORMAT") ]
+	#[ clap (help = "How to lay out the problems found") ]
+	format: Format,
+
+	#[ clap (long) ]
+	#[ clap (help = "Show each line with problems, with whitespace made visible") ]
+	snippets: bool,
+
+	#[ clap (long) ]
+	#[ clap (help = "Show the number of problems found by each rule, and the worst files") ]
+	stats: bool,
+
+	#[ clap (long, arg_enum, value_name = "LEVEL") ]
+	#[ clap (help = "Log where settings came from and why files were skipped, instead of using RUST_LOG") ]
+	log_level: Option <Level>,
+
+	#[ clap (help = "List of files to process, or - for stdin, defaulting to the current directory") ]
 	file: Vec <String>,
 
+	#[ clap (subcommand) ]
+	command: Option <Command>,
+
 }
 
-#[ derive (Clone, clap::Args) ]
-struct Config {
+/// Subcommands. Options can be given before or after them, and without one
+/// files are fixed.
+#[ derive (clap::Subcommand) ]
+enum Command {
 
-	#[ clap (long) ]
-	#[ clap (env = "FIX_WHITESPACE_EXPAND_TABS") ]
-	#[ clap (help = "Expand tabs into spaces") ]
-	expand_tabs: bool,
+	#[ clap (about = "Report problems without changing any files") ]
+	Check,
+
+	#[ clap (about = "Fix problems in place, which is the default") ]
+	Fix,
+
+	#[ clap (about = "List the rules, and whether they are fixable and enabled") ]
+	ListRules,
 
-	#[ clap (long, default_value = "4") ]
-	#[ clap (env = "FIX_WHITESPACE_TAB_SIZE") ]
-	#[ clap (help = "Tab size when expanding or calculating line length") ]
-	tab_size: usize,
+	#[ clap (about = "Install a git pre-commit hook which runs fix-whitespace --hook") ]
+	InstallHook,
 
-	#[ clap (long, env = "FIX_WHITESPACE_LINE_LENGTH", default_value = "80") ]
-	#[ clap (help = "Maximum line length") ]
-	line_length: usize,
+	#[ clap (about = "Run a language server on stdin and stdout, for editor integration") ]
+	Lsp,
 
 }
 
-struct CheckResult {
-	fixable_errors: u64,
-	unfixable_errors: u64,
+/// Exit statuses. Zero means every file is clean, or was fixed.
+const EXIT_FINDINGS: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_IO: i32 = 3;
+
+const PRE_COMMIT_HOOK: & str = "#!/bin/sh\nexec fix-whitespace --hook\n";
+
+#[ derive (Clone, Copy, PartialEq, Eq, clap::ArgEnum) ]
+enum ColourChoice {
+	Auto,
+	Always,
+	Never,
 }
 
-impl AddAssign for CheckResult {
+impl ColourChoice {
+
+	/// Whether to use colour for output to a stream, following the
+	/// conventions of the `NO_COLOR` and `TERM` variables.
+	fn enabled (
+		self,
+		is_terminal: bool,
+	) -> bool {
+
+		match self {
+			ColourChoice::Always => true,
+			ColourChoice::Never => false,
+			ColourChoice::Auto =>
+				is_terminal
+					&& env::var_os ("NO_COLOR").is_none_or (|value| value.is_empty ())
+					&& env::var_os ("TERM").is_none_or (|term| term != "dumb"),
+		}
 
-	fn add_assign (
-		& mut self,
-		other: CheckResult,
-	) {
-		self.fixable_errors += other.fixable_errors;
-		self.unfixable_errors += other.unfixable_errors;
 	}
 
 }
 
-fn find_modeline (
-	input: & mut dyn Read,
-) -> Result <Option <String>, String> {
+/// How to read and write files.
+struct FileOptions <'a> {
+	max_filesize: Option <u64>,
+	write: bool,
+	backup_suffix: Option <& 'a str>,
+	force: bool,
+	preserve_mtime: bool,
+	output_dir: Option <& 'a str>,
+	follow_symlinks: bool,
+	break_hardlinks: bool,
+	confirm: Cell <Confirm>,
+	discovered: & 'a HashSet <String>,
+}
+
+/// Which files to fix, which changes as questions are answered in
+/// interactive mode.
+#[ derive (Clone, Copy, PartialEq, Eq) ]
+enum Confirm {
+	Each,
+	All,
+	None,
+}
+
+fn do_file (
+	config: & Config,
+	config_file: Option <& ConfigFile>,
+	registry: & mut Registry,
+	line_ranges: Option <& LineRanges>,
+	reporter: & Reporter,
+	file_options: & FileOptions,
+	filename: & str,
+) -> Result <Vec <Finding>, Error> {
 
-	let modeline_regex = match Regex::new (r" (vim|vi|ex): (.+)") {
-		Ok (regex) => regex,
-		Err (error) => return Err (format! ("Regex error: {}", error)),
+	// open file
+
+	let mut file = match File::open (filename) {
+		Ok (file) => file,
+		Err (error) => return Err (Error::io ("opening", filename, error)),
 	};
 
-	let buf_reader = BufReader::new (input);
-	let mut modeline: Option <String> = None;
+	if let Some (max_filesize) = file_options.max_filesize {
+
+		let size = match file.metadata () {
+			Ok (metadata) => metadata.len (),
+			Err (error) => return Err (Error::io ("reading metadata for", filename, error)),
+		};
+
+		if size > max_filesize {
+			log::info (format_args! ("file={:?} skipped=too-large size={}", filename, size));
+			eprintln! ("Skipping {}, which is larger than {} bytes", filename, max_filesize);
+			return Ok (Vec::new ());
+		}
 
-	for line_result in buf_reader.lines () {
+	}
 
-		let line = match line_result {
-			Ok (line) => line,
-			Err (error) => return Err (format! ("{}", error)),
+	// files which were found rather than named, eg by searching a directory,
+	// are skipped if they turn out to be binary
+
+	let discovered = file_options.discovered.contains (filename);
+
+	let mut contents = String::new ();
+
+	if let Err (error) = file.read_to_string (& mut contents) {
+		if discovered && error.kind () == io::ErrorKind::InvalidData {
+			log::info (format_args! ("file={:?} skipped=binary", filename));
+			return Ok (Vec::new ());
+		}
+		return Err (Error::io ("reading", filename, error));
+	}
+
+	if discovered && contents.contains ('\0') {
+		log::info (format_args! ("file={:?} skipped=binary", filename));
+		return Ok (Vec::new ());
+	}
+
+	let mut config = config.for_file (config_file, filename, & contents);
+	config.line_ranges = line_ranges.cloned ();
+
+	// look for and correct problems, except in generated files, which are
+	// still copied to the output directory
+
+	let (fixed_contents, mut findings) =
+		if ! config.include_generated && generated::is_generated (& contents) {
+			log::info (format_args! ("file={:?} skipped=generated", filename));
+			if reporter.verbosity != Verbosity::Quiet {
+				eprintln! ("Not checking {}, which is marked as generated", filename);
+			}
+			(contents.clone (), Vec::new ())
+		} else {
+			registry.run (& config, & contents)
 		};
 
-		if let Some (captures) = modeline_regex.captures (& line) {
-			modeline = Some (
-				captures.get (2).unwrap ().as_str ().to_owned (),
-			);
+	// replacing a symlink would turn it into a regular file, so the file it
+	// points to is fixed instead, but only if asked. replacing a file with
+	// hard links would separate it from the other links.
+
+	let is_symlink = match fs::symlink_metadata (filename) {
+		Ok (metadata) => metadata.file_type ().is_symlink (),
+		Err (error) => return Err (Error::io ("reading metadata for", filename, error)),
+	};
+
+	let metadata = match fs::metadata (filename) {
+		Ok (metadata) => metadata,
+		Err (error) => return Err (Error::io ("reading metadata for", filename, error)),
+	};
+
+	let refusal = refusal (file_options, is_symlink, & metadata);
+
+	// a file is only rewritten if fixing it changes it, so its modification
+	// time is kept even if a rule reports something it then leaves alone
+
+	let changed = fixed_contents != contents;
+
+	let fixable = file_options.write && changed
+		&& findings.iter ().any (|finding| finding.fixable);
+
+	if file_options.write && ! changed && ! findings.is_empty () {
+		log::debug (format_args! ("file={:?} not-written=unchanged", filename));
+	}
+
+	let declined = match (refusal, file_options.confirm.get ()) {
+		(Some (refusal), _) if fixable => {
+			eprintln! ("Not fixing {}, {}", filename, refusal);
+			true
+		},
+		(_, Confirm::Each) if fixable => {
+			for line in reporter.diff (filename, & contents, & fixed_contents) {
+				println! ("{}", line);
+			}
+			! confirm_fix (filename, & file_options.confirm) ?
+		},
+		(_, Confirm::None) => fixable,
+		_ => false,
+	};
+
+	// problems which aren't fixed are reported as such, including when only
+	// checking
+
+	if declined || ! file_options.write {
+		for finding in & mut findings {
+			finding.fixable = false;
 		}
+	}
 
+	for line in reporter.findings (filename, & contents, & findings) {
+		println! ("{}", line);
 	}
 
-	Ok (modeline)
+	let modified = if file_options.preserve_mtime {
+		match metadata.modified () {
+			Ok (modified) => Some (modified),
+			Err (error) => return Err (Error::io ("reading modification time for", filename, error)),
+		}
+	} else {
+		None
+	};
 
-}
+	// every file is written to the output directory, fixed or not, so it has
+	// a complete copy
 
-fn config_from_modeline (
-	mut config: Rc <Config>,
-	modeline: & str,
-) -> Rc <Config> {
+	if let Some (output_dir) = file_options.output_dir {
 
-	for modeline_part in modeline.split (' ') {
+		let output_path = output_path (output_dir, filename) ?;
 
-		if modeline_part == "et" {
-			Rc::make_mut (& mut config).expand_tabs = true;
+		if let Some (parent) = output_path.parent () {
+			if let Err (error) = fs::create_dir_all (parent) {
+				return Err (Error::io ("creating", parent.display ().to_string (), error));
+			}
 		}
 
-		if modeline_part == "noet" {
-			Rc::make_mut (& mut config).expand_tabs = false;
+		let output_contents = if declined { & contents } else { & fixed_contents };
+
+		replace_file (
+			& output_path.to_string_lossy (),
+			filename,
+			output_contents,
+			& metadata,
+			modified) ?;
+
+		return Ok (findings);
+
+	}
+
+	if ! fixable || declined {
+		return Ok (findings);
+	}
+
+	let target = if is_symlink {
+		match fs::canonicalize (filename) {
+			Ok (target) => target.to_string_lossy ().into_owned (),
+			Err (error) => return Err (Error::io ("resolving", filename, error)),
 		}
+	} else {
+		filename.to_owned ()
+	};
 
-		if modeline_part.starts_with ("ts=") {
-			if let Ok (tab_size) = modeline [3 .. ].parse::<usize> () {
-				Rc::make_mut (& mut config).tab_size = tab_size;
+	let filename = target.as_str ();
+
+	// keep the original, but never replace an earlier backup by accident
+
+	if let Some (backup_suffix) = file_options.backup_suffix {
+
+		let backup_filename = format! ("{}{}", filename, backup_suffix);
+
+		// an old backup is removed rather than overwritten, as it may be
+		// read-only
+
+		if file_options.force {
+			if let Err (error) = fs::remove_file (& backup_filename) {
+				if error.kind () != io::ErrorKind::NotFound {
+					return Err (Error::io ("removing", backup_filename, error));
+				}
 			}
 		}
 
+		let mut backup = match OpenOptions::new ()
+				.write (true)
+				.create_new (true)
+				.open (& backup_filename) {
+			Ok (file) => file,
+			Err (error) => return Err (Error::io ("creating backup", backup_filename, error)),
+		};
+
+		if let Err (error) = backup.write_all (contents.as_bytes ()) {
+			return Err (Error::io ("writing", backup_filename, error));
+		}
+
+		if let Err (error) = fs::set_permissions (& backup_filename, metadata.permissions ()) {
+			return Err (Error::io ("setting permissions for", backup_filename, error));
+		}
+
 	}
 
-	config
+	replace_file (filename, filename, & fixed_contents, & metadata, modified) ?;
+
+	Ok (findings)
 
 }
 
-fn check_line (
-	config: & Config,
-	line: & str,
-) -> CheckResult {
+/// Where to write the fixed copy of a file in the output directory, at the
+/// same path relative to the current directory.
+fn output_path (
+	output_dir: & str,
+	filename: & str,
+) -> Result <PathBuf, Error> {
+
+	let outside_error = || Error::Usage (format! (
+		"{} is outside the current directory, so it can't be written to the output directory",
+		filename));
 
-	let mut check_result = CheckResult {
-		fixable_errors: 0,
-		unfixable_errors: 0,
+	let current_dir = match env::current_dir () {
+		Ok (current_dir) => current_dir,
+		Err (error) => return Err (Error::io ("reading", "current directory", error)),
 	};
 
-	if config.expand_tabs && line.contains ('\t') {
-		check_result.fixable_errors += 1
-	}
+	let path = Path::new (filename);
+	let path = path.strip_prefix (& current_dir).unwrap_or (path);
 
-	if ! config.expand_tabs
-		&& line.chars ()
-			.skip_while (|character| * character == '\t')
-			.any (|character| character == '\t') {
+	let mut output_path = PathBuf::from (output_dir);
 
-		check_result.unfixable_errors += 1;
+	for component in path.components () {
+		match component {
+			Component::Normal (name) => output_path.push (name),
+			Component::CurDir => (),
+			_ => return Err (outside_error ()),
+		}
 	}
 
-	if line.ends_with ('\r') {
-		check_result.fixable_errors += 1
-	}
+	Ok (output_path)
+
+}
+
+/// Ask whether to fix a file, after showing the changes. Answering "a" or
+/// "q" fixes, or doesn't fix, this and every remaining file.
+fn confirm_fix (
+	filename: & str,
+	confirm: & Cell <Confirm>,
+) -> Result <bool, Error> {
+
+	loop {
+
+		eprint! ("Fix {}? [y,n,a,q,?] ", filename);
+
+		let mut answer = String::new ();
+
+		if let Err (error) = io::stdin ().read_line (& mut answer) {
+			return Err (Error::io ("reading", "stdin", error));
+		}
+
+		match answer.trim () {
+			"y" => return Ok (true),
+			"n" => return Ok (false),
+			"a" => {
+				confirm.set (Confirm::All);
+				return Ok (true);
+			},
+			"q" => {
+				confirm.set (Confirm::None);
+				return Ok (false);
+			},
+			_ if answer.is_empty () => {
+				eprintln! ();
+				confirm.set (Confirm::None);
+				return Ok (false);
+			},
+			_ => eprintln! (
+				"y - fix this file\n\
+				n - don't fix this file\n\
+				a - fix this file and all the rest\n\
+				q - don't fix this file or any of the rest"),
+		}
 
-	if line.ends_with ("\r\n") {
-		check_result.fixable_errors += 1
 	}
 
-	if line.len () > 1 {
+}
 
-		let mut line_chars = line.chars ();
-		let last_ch_0 = line_chars.next_back ().unwrap ();
-		let last_ch_1 = line_chars.next_back ().unwrap ();
+/// Ask whether to fix more than some number of files, which can only be
+/// answered from a terminal.
+fn confirm_many (
+	confirm_limit: usize,
+) -> bool {
+
+	if ! io::stdin ().is_terminal () {
+		eprintln! (
+			"Not fixing more than {} in the current directory, without --yes",
+			plural (confirm_limit as u64, "file"));
+		return false;
+	}
 
-		if let Some (last_ch_2) = line_chars.next_back () {
+	loop {
 
-			if (last_ch_2.is_whitespace () && (last_ch_1, last_ch_2) == ('\r', '\n'))
-					|| (last_ch_1.is_whitespace () && last_ch_0 == '\n')
-					|| (last_ch_1.is_whitespace () && last_ch_0 == '\r') {
-				check_result.fixable_errors += 1
-			}
+		eprint! (
+			"Fix more than {} in the current directory? [y,n] ",
+			plural (confirm_limit as u64, "file"));
 
-		} else {
+		let mut answer = String::new ();
 
-			if (last_ch_1.is_whitespace () && last_ch_0 == '\n')
-					|| (last_ch_1.is_whitespace () && last_ch_0 == '\r') {
-				check_result.fixable_errors += 1
-			}
+		if io::stdin ().read_line (& mut answer).is_err () || answer.is_empty () {
+			eprintln! ();
+			return false;
+		}
 
+		match answer.trim () {
+			"y" => return true,
+			"n" => return false,
+			_ => eprintln! ("y - fix the files\nn - only report the problems"),
 		}
 
 	}
 
-	let line_len = line.len ()
-		+ line.matches ('\t').count () * (config.tab_size - 1) - 1;
+}
 
-	if line_len > config.line_length {
-		check_result.unfixable_errors += 1
+/// Why a file won't be replaced with its fixed contents, if it won't.
+fn refusal (
+	file_options: & FileOptions,
+	is_symlink: bool,
+	metadata: & fs::Metadata,
+) -> Option <& 'static str> {
+
+	if file_options.output_dir.is_some () {
+		None
+	} else if is_symlink && ! file_options.follow_symlinks {
+		Some ("which is a symlink, without --follow-symlinks")
+	} else if link_count (metadata) > 1 && ! file_options.break_hardlinks {
+		Some ("which has hard links, without --break-hardlinks")
+	} else if metadata.permissions ().readonly () && ! file_options.force {
+		Some ("which is read-only, without --force")
+	} else {
+		None
 	}
 
-	check_result
-
 }
 
-fn check_file (
+/// Count the files which fixing would change, up to a limit. Files which
+/// can't be read are left for processing to report, and those which would be
+/// refused aren't counted.
+fn count_changed_files (
 	config: & Config,
-	input: & mut dyn Read,
-) -> Result <CheckResult, String> {
+	config_file: Option <& ConfigFile>,
+	registry: & mut Registry,
+	file_options: & FileOptions,
+	filenames: & [String],
+	limit: usize,
+) -> usize {
 
-	let mut buf_reader = BufReader::new (input);
-	let mut line = String::new ();
+	let mut changed_files = 0;
 
-	let mut check_result = CheckResult {
-		fixable_errors: 0,
-		unfixable_errors: 0,
-	};
+	for filename in filenames {
 
-	loop {
+		if changed_files >= limit {
+			break;
+		}
 
-		line.truncate (0);
+		let is_symlink = fs::symlink_metadata (filename)
+			.is_ok_and (|metadata| metadata.file_type ().is_symlink ());
 
-		match buf_reader.read_line (& mut line) {
+		let metadata = match fs::metadata (filename) {
+			Ok (metadata) if refusal (file_options, is_symlink, & metadata).is_none () => metadata,
+			_ => continue,
+		};
 
-			Ok (0) =>
-				return Ok (check_result),
+		let too_large = file_options.max_filesize
+			.is_some_and (|max_filesize| metadata.len () > max_filesize);
 
-			Ok (_) =>
-				check_result += check_line (config, & line),
+		let contents = match fs::read_to_string (filename) {
+			Ok (contents) if ! too_large => contents,
+			_ => continue,
+		};
 
-			Err (error) =>
-				return Err (format! ("{}", error)),
+		let config = config.for_file (config_file, filename, & contents);
 
-		};
+		if ! config.include_generated && generated::is_generated (& contents) {
+			continue;
+		}
+
+		let (fixed_contents, findings) = registry.run (& config, & contents);
+
+		if fixed_contents != contents && findings.iter ().any (|finding| finding.fixable) {
+			changed_files += 1;
+		}
 
 	}
 
+	changed_files
+
 }
 
-fn fix_line <'a> (
-	config: & Config,
+fn has_extension (
 	filename: & str,
-	line_number: u64,
-	line: & 'a str,
-) -> Cow <'a, str> {
+	extensions: & [& str],
+) -> bool {
+
+	Path::new (filename).extension ()
+		.and_then (|extension| extension.to_str ())
+		.is_some_and (|extension| extensions.contains (& extension))
+
+}
 
-	let check_result = check_line (config, & line);
+/// Number of hard links to a file, which is always one where this isn't
+/// supported.
+fn link_count (
+	metadata: & fs::Metadata,
+) -> u64 {
 
-	if check_result.fixable_errors == 0 && check_result.unfixable_errors == 0 {
-		return Cow::Borrowed (line);
+	#[ cfg (unix) ]
+	{
+		use std::os::unix::fs::MetadataExt as _;
+		metadata.nlink ()
 	}
 
-	let mut modified_line = Cow::Borrowed (line);
-	let mut fixes_applied: Vec <& 'static str> = Vec::new ();
+	#[ cfg (not (unix)) ]
+	{
+		let _ = metadata;
+		1
+	}
 
-	// fix line ending
+}
 
-	if modified_line.ends_with ('\r') {
+/// Replace a file's contents atomically, by writing a temporary file in the
+/// same directory and renaming it over the original. The temporary file gets
+/// the original's permissions and extended attributes, and its owner and
+/// group where allowed, and is removed if anything fails. Read-only files can
+/// be replaced this way too, and stay read-only. The original is normally the
+/// same file, but is the source file when writing to an output directory.
+fn replace_file (
+	filename: & str,
+	original_filename: & str,
+	contents: & str,
+	metadata: & fs::Metadata,
+	modified: Option <SystemTime>,
+) -> Result <(), Error> {
+
+	let (temp_filename, mut temp_file) = create_temp_file (filename) ?;
+
+	let original = File::open (original_filename).ok ();
+
+	let result = write_temp_file (
+			& temp_filename,
+			& mut temp_file,
+			original.as_ref (),
+			contents,
+			metadata,
+			modified)
+		.and_then (|_| fs::rename (& temp_filename, filename)
+			.map_err (|error| Error::io (
+				"renaming",
+				format! ("{} to {}", temp_filename, filename),
+				error)));
+
+	if result.is_err () {
+		let _ = fs::remove_file (& temp_filename);
+	}
 
-		modified_line = {
+	result
 
-			let mut line_chars = modified_line.chars ();
-			line_chars.next_back ();
+}
 
-			Cow::Owned (
-				line_chars
-					.chain (Some ('\n'))
-					.collect::<String> ())
+/// Create a temporary file next to the given one, with a name which isn't
+/// already in use.
+fn create_temp_file (
+	filename: & str,
+) -> Result <(String, File), Error> {
 
-		};
+	let path = Path::new (filename);
 
-		fixes_applied.push ("fixed mac line ending");
+	let name = match path.file_name () {
+		Some (name) => name.to_string_lossy (),
+		None => return Err (Error::Usage (format! ("Not a file: {}", filename))),
+	};
+
+	for attempt in 0 .. {
+
+		let temp_filename = path
+			.with_file_name (format! (".{}.{}-{}.tmp", name, process::id (), attempt))
+			.to_string_lossy ()
+			.into_owned ();
+
+		match OpenOptions::new ().write (true).create_new (true).open (& temp_filename) {
+			Ok (file) => return Ok ((temp_filename, file)),
+			Err (error) if error.kind () == io::ErrorKind::AlreadyExists => continue,
+			Err (error) => return Err (Error::io ("creating", temp_filename, error)),
+		}
 
 	}
 
-	if modified_line.ends_with ("\r\n") {
+	unreachable! ()
+
+}
 
-		modified_line = {
+fn write_temp_file (
+	temp_filename: & str,
+	temp_file: & mut File,
+	original: Option <& File>,
+	contents: & str,
+	metadata: & fs::Metadata,
+	modified: Option <SystemTime>,
+) -> Result <(), Error> {
+
+	if let Err (error) = temp_file.write_all (contents.as_bytes ()) {
+		return Err (Error::io ("writing", temp_filename, error));
+	}
 
-			let mut line_chars = modified_line.chars ();
+	// only root can give files away, but the group can often be kept, so
+	// failures are ignored. this comes first as it can clear setuid bits.
 
-			line_chars.next_back ();
-			line_chars.next_back ();
+	#[ cfg (unix) ]
+	{
+		use std::os::unix::fs::MetadataExt as _;
+		use std::os::unix::fs::fchown;
+		if fchown (& * temp_file, Some (metadata.uid ()), Some (metadata.gid ())).is_err () {
+			let _ = fchown (& * temp_file, None, Some (metadata.gid ()));
+		}
+	}
 
-			Cow::Owned (
-				line_chars
-					.chain (Some ('\n'))
-					.collect::<String> ())
+	// attributes are copied before the permissions, which could stop them
+	// being set
 
-		};
+	if let Some (original) = original {
+		xattr::copy (original, temp_file);
+	}
 
-		fixes_applied.push ("fixed windows line ending");
+	if let Err (error) = temp_file.set_permissions (metadata.permissions ()) {
+		return Err (Error::io ("setting permissions for", temp_filename, error));
+	}
 
+	if let Some (modified) = modified {
+		if let Err (error) = temp_file.set_modified (modified) {
+			return Err (Error::io ("setting modification time for", temp_filename, error));
+		}
 	}
 
-	// expand tabs
+	if let Err (error) = temp_file.sync_all () {
+		return Err (Error::io ("syncing", temp_filename, error));
+	}
 
-	if config.expand_tabs && modified_line.contains ('\t') {
+	Ok (())
 
-		let tab_as_spaces =
-			iter::repeat (" ")
-				.take (config.tab_size)
-				.collect::<String> ();
+}
 
-		modified_line = Cow::Owned (
-			modified_line.replace ("\t", & tab_as_spaces));
+/// Fix text from stdin and write it to stdout, resolving settings as if it
+/// came from the given file. Findings go to stderr so only the fixed text is
+/// written to stdout.
+fn do_stdin (
+	config: & Config,
+	config_file: Option <& ConfigFile>,
+	registry: & mut Registry,
+	reporter: & Reporter,
+	write: bool,
+	filename: & str,
+) -> Result <Vec <Finding>, Error> {
 
-		fixes_applied.push ("expanded tabs");
+	let mut contents = String::new ();
 
+	if let Err (error) = io::stdin ().read_to_string (& mut contents) {
+		return Err (Error::io ("reading", "stdin", error));
 	}
 
-	// detect tabs after other characters
+	let config = config.for_file (config_file, filename, & contents);
+
+	let (fixed_contents, mut findings) =
+		if ! config.include_generated && generated::is_generated (& contents) {
+			log::info (format_args! ("file={:?} skipped=generated", filename));
+			if reporter.verbosity != Verbosity::Quiet {
+				eprintln! ("Not checking {}, which is marked as generated", filename);
+			}
+			(contents.clone (), Vec::new ())
+		} else {
+			registry.run (& config, & contents)
+		};
 
-	if ! config.expand_tabs
-			&& modified_line.chars ()
-				.skip_while (|character| * character == '\t')
-				.any (|character| character == '\t') {
-		fixes_applied.push ("tabs after other characters");
+	if ! write {
+		for finding in & mut findings {
+			finding.fixable = false;
+		}
 	}
 
-	// fix whitespace at end
+	for line in reporter.findings (filename, & contents, & findings) {
+		eprintln! ("{}", line);
+	}
 
-	if modified_line.len () > 1 {
+	if write {
+		if let Err (error) = io::stdout ().write_all (fixed_contents.as_bytes ()) {
+			return Err (Error::io ("writing", "stdout", error));
+		}
+	}
 
-		let last_character = {
+	Ok (findings)
 
-			let mut line_chars = modified_line.chars ();
+}
 
-			line_chars.next_back ();
-			line_chars.next_back ().unwrap ()
+fn apply_config_file (
+	config: & mut Config,
+	config_file: & ConfigFile,
+	matches: & ArgMatches,
+) -> Result <(), Error> {
 
-		};
+	let config_error = |message| Error::Config {
+		path: config_file.path.clone (),
+		message,
+	};
 
-		if last_character.is_whitespace () {
+	// top level settings only apply where not given on the command line,
+	// apart from severities and patterns for lines to ignore, which are added
+	// before those on the command line, as later severities take precedence
 
-			fixes_applied.push ("removed whitespace from end");
+	let command = Args::command ();
 
-			modified_line = {
+	let is_explicit = |key: & str| command.get_arguments ()
+		.find (|arg| arg.get_long () == Some (key))
+		.and_then (|arg| matches.value_source (arg.get_id ()))
+		.is_some_and (|value_source| value_source != ValueSource::DefaultValue);
 
-				let mut line_chars = modified_line.chars ();
-				line_chars.next_back ();
+	let is_list = |key: & str|
+		key == "severity" || key.starts_with ("severity.") || key == "ignore-line-pattern";
 
-				let modified_line_temp;
+	let command_line_severity = mem::take (& mut config.severity);
+	let command_line_ignore_line_pattern = mem::take (& mut config.ignore_line_pattern);
 
-				loop {
-					match line_chars.next_back () {
+	config.apply (
+		config_file.settings.iter ()
+			.filter (|setting| {
+				let explicit = ! is_list (& setting.key) && is_explicit (& setting.key);
+				if explicit {
+					log::debug (format_args! (
+						"key={} source=command-line overridden=config-file",
+						setting.key));
+				}
+				! explicit
+			}))
+		.map_err (config_error) ?;
 
-						Some (next_last_character) => if ! next_last_character.is_whitespace () {
-							modified_line_temp =
-								line_chars
-									.chain (Some (next_last_character))
-									.chain (Some ('\n'))
-									.collect::<String> ();
-							break;
-						},
+	config.severity.extend (command_line_severity);
+	config.ignore_line_pattern.extend (command_line_ignore_line_pattern);
 
-						None => {
-							modified_line_temp = "\n".to_string ();
-							break;
-						},
+	// check section settings now, rather than for each file
 
-					}
-				}
+	for (_, settings) in & config_file.filetypes {
+		config.clone ().apply (settings).map_err (config_error) ?;
+	}
+
+	for glob_section in config_file.globs.iter ().chain (& config_file.paths) {
+		config.clone ().apply (& glob_section.settings).map_err (config_error) ?;
+	}
 
-				Cow::Owned (modified_line_temp)
+	Ok (())
 
-			};
+}
+
+fn check_rule_ids (
+	config: & Config,
+	registry: & Registry,
+) -> Result <(), Error> {
 
+	let severity_ids = config.severity.iter ()
+		.map (|rule_severity| & rule_severity.rule);
+
+	for id in config.enable.iter ().chain (& config.disable).chain (severity_ids) {
+		if ! registry.rules ().any (|rule| rule.id () == id) {
+			return Err (Error::Usage (format! ("Unknown rule: {}", id)));
 		}
+	}
+
+	Ok (())
+
+}
+
+/// Print a table of the rules, with whether they are fixable and enabled by
+/// the top level settings. Sections in the config file can change these for
+/// particular files.
+fn list_rules (
+	config: & Config,
+	registry: & Registry,
+) {
+
+	let id_width = registry.rules ()
+		.map (|rule| rule.id ().len ())
+		.chain ([ "RULE".len () ])
+		.max ()
+		.unwrap_or_default ();
+
+	let yes_no = |value| if value { "yes" } else { "no" };
+
+	println! ("{:id_width$}  FIXABLE  ENABLED  DESCRIPTION", "RULE");
+
+	for rule in registry.rules () {
+		println! (
+			"{:id_width$}  {:7}  {:7}  {}",
+			rule.id (),
+			yes_no (rule.fixable (config)),
+			yes_no (config.is_rule_enabled (rule.id ())),
+			rule.description ());
+	}
+
+}
+
+fn install_hook (
+	force: bool,
+) -> Result <(), Error> {
 
+	let hook_path = git::hooks_dir () ?.join ("pre-commit");
+
+	if hook_path.exists () && ! force {
+		return Err (Error::Usage (format! (
+			"{} already exists, use --force to replace it",
+			hook_path.display ())));
 	}
 
-	// detect long lines
+	if let Some (hooks_dir) = hook_path.parent () {
+		if let Err (error) = fs::create_dir_all (hooks_dir) {
+			return Err (Error::io ("creating", hooks_dir.display ().to_string (), error));
+		}
+	}
 
-	let modified_line_len =
-		modified_line.len ()
-			+ modified_line.matches ('\t').count () * (config.tab_size - 1)
-			- 1;
+	if let Err (error) = fs::write (& hook_path, PRE_COMMIT_HOOK) {
+		return Err (Error::io ("writing", hook_path.display ().to_string (), error));
+	}
+
+	#[ cfg (unix) ]
+	{
+		use std::os::unix::fs::PermissionsExt as _;
 
-	if modified_line_len > config.line_length {
-		fixes_applied.push ("line too long");
+		if let Err (error) =
+				fs::set_permissions (& hook_path, fs::Permissions::from_mode (0o755)) {
+			return Err (Error::io (
+				"setting permissions for",
+				hook_path.display ().to_string (),
+				error));
+		}
 	}
 
-	// print a message
+	eprintln! ("Installed {}", hook_path.display ());
 
-	println! (
-		"{}:{}: {}",
-		filename,
-		line_number + 1,
-		fixes_applied.join (", "));
+	Ok (())
 
-	// return
+}
 
-	modified_line
+fn error_exit_code (
+	error: & Error,
+) -> i32 {
+	if error.is_usage () { EXIT_USAGE } else { EXIT_IO }
+}
+
+/// Report an error which stops the whole run.
+fn fail (
+	error: Error,
+) -> ! {
+
+	eprintln! ("{}", error);
+	process::exit (error_exit_code (& error));
 
 }
 
-fn fix_file (
-	config: & Config,
-	filename: & str,
-	input: & mut dyn Read,
-	output: & mut dyn Write,
-) -> Result <(), String> {
+/// Read a list of file names, eg from `git ls-files -z`. Empty names are
+/// ignored, so a trailing separator is optional.
+fn read_file_list (
+	path: & str,
+	separator: char,
+) -> Result <Vec <String>, Error> {
 
-	let mut buf_reader = BufReader::new (input);
-	let mut line = String::new ();
-	let mut line_number: u64 = 0;
+	let mut contents = String::new ();
 
-	loop {
+	let result = if path == "-" {
+		io::stdin ().read_to_string (& mut contents)
+	} else {
+		File::open (path).and_then (|mut file| file.read_to_string (& mut contents))
+	};
+
+	if let Err (error) = result {
+		return Err (Error::io ("reading", if path == "-" { "stdin" } else { path }, error));
+	}
 
-		line.truncate (0);
+	Ok (
+		contents.split (separator)
+			.filter (|filename| ! filename.is_empty ())
+			.map (str::to_owned)
+			.collect ())
 
-		match buf_reader.read_line (& mut line) {
+}
 
-			Ok (0) => return Ok (()),
-			Err (error) => return Err (format! ("{}", error)),
+/// Stage files which were fixed, returning false if any fixes couldn't be
+/// staged.
+fn finish_hook (
+	results: & [(& str, Option <CheckResult>)],
+	unstaged_files: & [String],
+) -> Result <bool, Error> {
 
-			Ok (_) => {
+	let mut fixed_files = Vec::new ();
+	let mut staged_all = true;
 
-				let output_line =
-					fix_line (
-						config,
-						filename,
-						line_number,
-						& line);
+	for & (filename, result) in results {
 
-				match output.write_all (& output_line.as_bytes ()) {
-					Ok (_) => (),
-					Err (error) => return Err (format! ("{}", error)),
-				};
+		if result.is_none_or (|result| result.fixable_errors == 0) {
+			continue;
+		}
 
-			},
+		if unstaged_files.iter ().any (|unstaged_file| unstaged_file == filename) {
+			eprintln! ("{} has unstaged changes, so fixes were not staged", filename);
+			staged_all = false;
+			continue;
 		}
 
-		line_number += 1;
+		fixed_files.push (filename);
 
 	}
 
+	if ! fixed_files.is_empty () {
+		git::add (& fixed_files) ?;
+	}
+
+	Ok (staged_all)
+
 }
 
-fn do_file (
-	config: Rc <Config>,
-	filename: & str,
-) {
+fn main () {
 
-	// open file
+	let mut command = Args::command ();
 
-	let mut file = match File::open (filename) {
-		Ok (file) => file,
-		Err (error) => {
-			println! ("Error opening {}: {}", filename, error);
-			return;
-		},
+	// options are global, so they can be given after a subcommand
+
+	let ids: Vec <& str> = command.get_arguments ()
+		.map (|arg| arg.get_id ())
+		.filter (|& id| id != "help" && id != "version")
+		.collect ();
+
+	for id in ids {
+		command = command.mut_arg (id, |arg| arg.global (true));
+	}
+
+	let matches = command.get_matches ();
+
+	let mut args = match Args::from_arg_matches (& matches) {
+		Ok (args) => args,
+		Err (error) => error.exit (),
 	};
 
-	// first pass - look for modeline
+	log::set_level (
+		args.log_level
+			.or_else (|| env::var ("RUST_LOG").ok ()
+				.and_then (|filter| Level::from_filter (& filter)))
+			.unwrap_or (Level::Off));
 
-	let modeline = match find_modeline (& mut file) {
-		Ok (modeline) => modeline,
-		Err (error) => {
-			println! ("Error reading {}: {}", filename, error);
-			return;
-		},
+	if let Some (Command::InstallHook) = args.command {
+		if let Err (error) = install_hook (args.force) {
+			fail (error);
+		}
+		return;
+	}
+
+	// load config file
+
+	let config_file = match args.config_file {
+		Some (ref path) => ConfigFile::load (Path::new (path)).map (Some),
+		None => ConfigFile::find (),
 	};
 
-	let config = match modeline {
-		Some (modeline) => config_from_modeline (config, & modeline),
-		None => config,
+	let config_file = match config_file {
+		Ok (config_file) => config_file,
+		Err (error) => fail (error),
 	};
 
-	// second pass - look for problems
+	if let Some (ref config_file) = config_file {
+		log::info (format_args! ("config-file={:?}", config_file.path));
+		if let Err (error) =
+				apply_config_file (& mut args.config, config_file, & matches) {
+			fail (error);
+		}
+	}
 
-	if let Err (error) = file.seek (SeekFrom::Start (0)) {
-		println! ("Error reading {}: {}", filename, error);
+	// custom rules from the config file run before the built in ones
+
+	let mut registry = Registry::new ();
+
+	if let Some (ref config_file) = config_file {
+		for rule in & config_file.rules {
+			registry.add (rule.clone ());
+		}
+	}
+
+	registry.add_builtin ();
+
+	if let Err (error) = check_rule_ids (& args.config, & registry) {
+		fail (error);
+	}
+
+	if let Some (Command::ListRules) = args.command {
+		list_rules (& args.config, & registry);
+		return;
+	}
+
+	if let Some (Command::Lsp) = args.command {
+		let mut server = lsp::Server::new (args.config, config_file, registry);
+		let stdin = io::stdin ();
+		if let Err (error) = server.run (& mut stdin.lock (), & mut io::stdout ()) {
+			fail (error);
+		}
 		return;
 	}
 
-	let check_result = match check_file (& config, & mut file) {
-		Ok (check_result) => {
-			if check_result.fixable_errors == 0
-					&& check_result.unfixable_errors == 0 {
-				return;
+	// fixing a file with unstaged changes would stage those changes too, so
+	// they are found before anything is changed
+
+	let unstaged_files = if args.hook {
+		match git::unstaged_files () {
+			Ok (unstaged_files) => unstaged_files,
+			Err (error) => fail (error),
+		}
+	} else {
+		Vec::new ()
+	};
+
+	// ask git for files to process
+
+	let git_files = if args.hook {
+		git::staged_files ()
+	} else if args.git_modified {
+		git::modified_files ()
+	} else if args.git_tracked {
+		git::tracked_files ()
+	} else {
+		Ok (Vec::new ())
+	};
+
+	// files which were found rather than named are skipped if they are
+	// binary, and the cache and backups are left out when untracked files are
+	// included
+
+	let mut discovered = HashSet::new ();
+
+	let cache_path = paths::absolute (Path::new (& args.cache_location));
+	let backup_suffix = args.backup.as_ref ()
+		.map (|suffix| suffix.as_deref ().unwrap_or (".orig").to_owned ());
+
+	let is_own_file = |filename: & String|
+		filename.ends_with (".orig")
+			|| backup_suffix.as_ref ().is_some_and (|suffix| filename.ends_with (suffix.as_str ()))
+			|| paths::absolute (Path::new (filename)) == cache_path;
+
+	match git_files {
+		Ok (mut git_files) => {
+			if args.git_modified {
+				git_files.retain (|filename| ! is_own_file (filename));
 			}
-			check_result
+			discovered.extend (git_files.iter ().cloned ());
+			args.file.extend (git_files);
 		},
-		Err (error) => {
-			println! ("Error reading {}: {}", filename, error);
-			return;
+		Err (error) => fail (error),
+	}
+
+	if args.stats && args.format == Format::Tsv {
+		fail (Error::Usage ("The tsv format only shows problems, so can't be used with \
+			--stats".to_owned ()));
+	}
+
+	let check = matches! (args.command, Some (Command::Check));
+
+	if check && (args.hook || args.interactive || args.backup.is_some ()
+			|| args.output_dir.is_some ()) {
+		fail (Error::Usage ("The check subcommand doesn't change any files, so can't be used \
+			with --hook, --interactive, --backup or --output-dir".to_owned ()));
+	}
+
+	if args.interactive && args.files_from.as_deref () == Some ("-") {
+		fail (Error::Usage ("Answers for --interactive are read from stdin, so it can't be \
+			used with --files-from -".to_owned ()));
+	}
+
+	// without any files, or another way to choose them, the current
+	// directory is processed, leaving out files which git ignores
+
+	let bare = args.file.is_empty () && args.files_from.is_none () && ! args.hook
+		&& ! args.git_modified && ! args.git_tracked && args.changed_lines.is_none ()
+		&& args.stdin_filepath.is_none ();
+
+	if bare {
+		if git::is_work_tree () {
+			match git::unignored_files () {
+				Ok (mut git_files) => {
+					git_files.retain (|filename| ! is_own_file (filename));
+					discovered.extend (git_files.iter ().cloned ());
+					args.file.extend (git_files);
+				},
+				Err (error) => fail (error),
+			}
+		} else {
+			args.file.push (".".to_owned ());
+		}
+	}
+
+	if let Some (ref files_from) = args.files_from {
+		match read_file_list (files_from, if args.null { '\0' } else { '\n' }) {
+			Ok (files) => args.file.extend (files),
+			Err (error) => fail (error),
+		}
+	}
+
+	// search directories for files
+
+	let mut files = Vec::new ();
+
+	for filename in args.file.drain ( .. ) {
+		if filename != "-" && Path::new (& filename).is_dir () {
+			match walk::files (& filename, args.follow_symlinks) {
+				Ok (mut dir_files) => {
+					if bare {
+						dir_files.retain (|filename| ! is_own_file (filename));
+					}
+					discovered.extend (dir_files.iter ().cloned ());
+					files.extend (dir_files);
+				},
+				Err (error) => fail (error),
+			}
+		} else {
+			files.push (filename);
+		}
+	}
+
+	args.file = files;
+
+	// find changed lines, and process the changed files if none were given
+
+	let changed_lines = match args.changed_lines {
+		Some (ref base) => match git::changed_lines (base.as_deref ()) {
+			Ok (changed_lines) => Some (changed_lines),
+			Err (error) => fail (error),
 		},
+		None => None,
 	};
 
-	// third pass - correct or report problems
+	// these are only the changed files under the current directory, as when
+	// asking git for files in other ways
+
+	if let Some (ref changed_lines) = changed_lines {
+		if args.file.is_empty () {
+			let current_dir = paths::absolute (Path::new ("."));
+			let changed_files: Vec <String> = changed_lines.iter ()
+				.filter_map (|(path, _)| path.strip_prefix (& current_dir).ok ())
+				.map (|path| path.to_string_lossy ().into_owned ())
+				.collect ();
+			discovered.extend (changed_files.iter ().cloned ());
+			args.file.extend (changed_files);
+		}
+	}
 
-	if let Err (error) = file.seek (SeekFrom::Start (0)) {
-		println! ( "Error reading {}: {}", filename, error);
-		return;
+	if ! args.ext.is_empty () {
+		let extensions: Vec <& str> = args.ext.iter ()
+			.map (|extension| extension.trim_start_matches ('.'))
+			.collect ();
+		args.file.retain (|filename| {
+			let keep = filename == "-" || has_extension (filename, & extensions);
+			if ! keep {
+				log::info (format_args! ("file={:?} skipped=extension", filename));
+			}
+			keep
+		});
 	}
 
-	if check_result.fixable_errors > 0 {
+	// check every file can be written to the output directory before any are
 
-		let output_filename = format! ("{}.tmp", filename);
+	if let Some (ref output_dir) = args.output_dir {
+		for filename in args.file.iter ().filter (|& filename| filename != "-") {
+			if let Err (error) = output_path (output_dir, filename) {
+				fail (error);
+			}
+		}
+	}
 
-		let mut output = match File::create (& output_filename) {
-			Ok (file) => file,
-			Err (error) => {
-				println! ("Error creating {}: {}", output_filename, error);
-				return;
+	if args.stdin_filepath.is_some () && args.file.is_empty () {
+		args.file.push ("-".to_owned ());
+	}
+
+	let verbosity = if args.quiet || args.files_with_errors {
+		Verbosity::Quiet
+	} else if args.verbose {
+		Verbosity::Verbose
+	} else {
+		Verbosity::Normal
+	};
+
+	let reporter = Reporter {
+		verbosity,
+		format: args.format,
+		colour: args.color.enabled (io::stdout ().is_terminal ()),
+		snippets: args.snippets,
+		explain: check,
+	};
+
+	// findings for stdin are written to stderr
+
+	let stdin_reporter = Reporter {
+		colour: args.color.enabled (io::stderr ().is_terminal ()),
+		.. reporter
+	};
+
+	let file_options = FileOptions {
+		max_filesize: (! args.no_max_filesize).then_some (args.max_filesize),
+		write: ! args.files_with_errors && ! check,
+		backup_suffix: args.backup.as_ref ()
+			.map (|suffix| suffix.as_deref ().unwrap_or (".orig")),
+		force: args.force,
+		preserve_mtime: args.preserve_mtime,
+		output_dir: args.output_dir.as_deref (),
+		follow_symlinks: args.follow_symlinks,
+		break_hardlinks: args.break_hardlinks,
+		confirm: Cell::new (if args.interactive { Confirm::Each } else { Confirm::All }),
+		discovered: & discovered,
+	};
+
+	// fixing lots of files without being given any may be a mistake, so it
+	// needs confirming, otherwise they are only reported
+
+	if bare && file_options.write && file_options.output_dir.is_none ()
+			&& ! args.interactive && ! args.yes {
+
+		let changed_files = count_changed_files (
+			& args.config,
+			config_file.as_ref (),
+			& mut registry,
+			& file_options,
+			& args.file,
+			args.confirm_limit + 1);
+
+		if changed_files > args.confirm_limit && ! confirm_many (args.confirm_limit) {
+			file_options.confirm.set (Confirm::None);
+		}
+
+	}
+
+	// the cache is only valid while everything affecting the results stays
+	// the same
+
+	let mut cache = if args.cache {
+		let settings = format! (
+			"{:?}\n{:?}\n{}",
+			args.config,
+			file_options.max_filesize,
+			config_file.as_ref ()
+				.and_then (|config_file| fs::read_to_string (& config_file.path).ok ())
+				.unwrap_or_default ());
+		match Cache::load (& args.cache_location, & settings) {
+			Ok (cache) => Some (cache),
+			Err (error) => fail (error),
+		}
+	} else {
+		None
+	};
+
+	let mut results = Vec::new ();
+	let mut statistics = Statistics::default ();
+	let mut total_errors = 0;
+
+	// every file is processed, even after errors, unless asked to stop
+
+	for filename in & args.file {
+
+		if cache.as_ref ().is_some_and (|cache| cache.is_clean (filename)) {
+			log::info (format_args! ("file={:?} skipped=cached-clean", filename));
+			results.push ((filename.as_str (), Some (CheckResult::from_findings (& []))));
+			continue;
+		}
+
+		let line_ranges = match changed_lines {
+			Some (ref changed_lines) => {
+				let path = paths::absolute (Path::new (filename));
+				match changed_lines.iter ().find (|(changed_path, _)| * changed_path == path) {
+					Some ((_, line_ranges)) => Some (line_ranges),
+					None => {
+						log::info (format_args! ("file={:?} skipped=no-changed-lines", filename));
+						continue;
+					},
+				}
 			},
+			None => None,
 		};
 
-		if let Err (error) = fix_file (& config, filename, & mut file, & mut output) {
-			println! ("Error fixing {}: {}", filename, error);
-			return;
-		}
+		let started = Instant::now ();
+
+		let result = if filename == "-" {
+			do_stdin (
+				& args.config,
+				config_file.as_ref (),
+				& mut registry,
+				& stdin_reporter,
+				! check,
+				args.stdin_filepath.as_deref ().unwrap_or ("-"))
+		} else {
+			do_file (
+				& args.config,
+				config_file.as_ref (),
+				& mut registry,
+				line_ranges,
+				& reporter,
+				& file_options,
+				filename)
+		};
+
+		log::debug (format_args! (
+			"file={:?} elapsed={:?} findings={}",
+			filename,
+			started.elapsed (),
+			result.as_ref ().map_or (0, Vec::len)));
 
-		let metadata = match std::fs::metadata (filename) {
-			Ok (metadata) => metadata,
+		let result = match result {
+			Ok (findings) => {
+				if args.files_with_errors && ! findings.is_empty () {
+					println! ("{}", filename);
+				}
+				statistics.add (filename, & findings);
+				if let Some (ref mut cache) = cache {
+					let clean = findings.iter ().all (|finding| finding.fixable)
+						&& (findings.is_empty () || file_options.write);
+					cache.set_clean (filename, clean);
+				}
+				Some (CheckResult::from_findings (& findings))
+			},
 			Err (error) => {
-				println! ("Error reading permissions for {}: {}", filename, error);
-				return;
+				eprintln! ("{}", error);
+				None
 			},
 		};
 
-		if let Err (error) =
-				fs::set_permissions (
-					& output_filename,
-					metadata.permissions ()) {
-			println! ("Error setting permissions for {}: {}", output_filename, error);
-			return;
+		let errors = result.as_ref ().map_or (1, CheckResult::remaining_errors);
+		total_errors += errors;
+
+		results.push ((filename.as_str (), result));
+
+		if args.fail_fast && errors > 0 {
+			eprintln! ("Stopping after the first file with problems");
+			break;
 		}
 
-		if let Err (error) = fs::rename (& output_filename, filename) {
-			println! ("Error renaming {} to {}: {}", output_filename, filename, error);
+		if args.max_errors.is_some_and (|max_errors| total_errors >= max_errors) {
+			eprintln! ("Stopping after {}", plural (total_errors, "problem"));
+			break;
 		}
 
-	} else {
+	}
 
-		if let Err (error) = fix_file (& config, filename, & mut file, & mut io::sink ()) {
-			println! ("Error fixing {}: {}", filename, error);
-			return;
-		};
+	if let Some (ref cache) = cache {
+		if let Err (error) = cache.save () {
+			eprintln! ("{}", error);
+		}
+	}
+
+	// stdout is for the fixed text when reading from stdin
+
+	let reading_stdin = args.file.iter ().any (|filename| filename == "-");
 
+	if verbosity != Verbosity::Quiet && args.format != Format::Tsv
+			&& ! results.is_empty () && ! reading_stdin {
+		let checked: Vec <CheckResult> =
+			results.iter ().filter_map (|& (_, result)| result).collect ();
+		println! ("{}", reporter.summary (& checked));
 	}
 
-}
+	if args.stats {
+		for line in statistics.report () {
+			if reading_stdin {
+				eprintln! ("{}", line);
+			} else {
+				println! ("{}", line);
+			}
+		}
+	}
 
-fn main () {
+	// errors take precedence over problems which weren't fixed
+
+	let mut exit_code = if results.iter ().any (|(_, result)| result.is_none ()) {
+		EXIT_IO
+	} else if results.iter ().flat_map (|(_, result)| result)
+			.any (|result| result.remaining_errors () > 0) {
+		EXIT_FINDINGS
+	} else {
+		0
+	};
+
+	if args.hook {
 
-	let args = Args::parse ();
-	let config = Rc::new (args.config);
+		match finish_hook (& results, & unstaged_files) {
+			Ok (true) => (),
+			Ok (false) => exit_code = exit_code.max (EXIT_FINDINGS),
+			Err (error) => {
+				eprintln! ("{}", error);
+				exit_code = exit_code.max (error_exit_code (& error));
+			},
+		}
+
+		if exit_code != 0 {
+			eprintln! ("Commit blocked by whitespace problems");
+		}
 
-	for filename in args.file {
-		do_file (config.clone (), & filename);
 	}
 
+	process::exit (exit_code);
+
 }
 
 // ex: noet ts=4 filetype=rust
@@ -0,0 +1,235 @@
+//! Vim style modelines, eg `vim: et ts=2` or `vim: set et ts=2 :`.
+//!
+//! As in vim, only the first and last few lines are searched, so that text
+//! such as `vi:` elsewhere in a file isn't mistaken for a modeline.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::config::Config;
+use crate::config::LineEnding;
+use crate::lines;
+use crate::lines::Lines;
+
+/// Number of lines at the start and end of a file which are searched, the
+/// same as vim's default.
+const MODELINE_LINES: usize = 5;
+
+/// Find the options in any modelines, separated by spaces.
+pub fn find (
+	contents: & str,
+) -> Option <String> {
+
+	let lines: Vec <& str> = Lines::new (contents).collect ();
+
+	let searched: Vec <& str> = if lines.len () <= MODELINE_LINES * 2 {
+		lines
+	} else {
+		lines [ .. MODELINE_LINES].iter ()
+			.chain (& lines [lines.len () - MODELINE_LINES .. ])
+			.copied ()
+			.collect ()
+	};
+
+	let options: Vec <String> = searched.into_iter ()
+		.filter_map (|line| parse (lines::split_line_ending (line).0))
+		.collect ();
+
+	if options.is_empty () {
+		return None;
+	}
+
+	Some (options.join (" "))
+
+}
+
+fn parse (
+	content: & str,
+) -> Option <String> {
+
+	lazy_static! {
+		static ref MODELINE_REGEX: Regex =
+			Regex::new (r"(?:^|\s)(?:vim?|Vim|ex)(?:[<=>]?[0-9]+)?:\s*(.*)").unwrap ();
+	}
+
+	let options = MODELINE_REGEX.captures (content) ?.get (1) ?.as_str ();
+
+	// the second form ends at a colon, and anything after it is ignored
+
+	let set_options = options.strip_prefix ("set ")
+		.or_else (|| options.strip_prefix ("se "));
+
+	let options: Vec <& str> = match set_options {
+		Some (set_options) => set_options [ .. set_options.find (':') ?]
+			.split_whitespace ()
+			.collect (),
+		None => options
+			.split (|character: char| character == ':' || character.is_whitespace ())
+			.filter (|option| ! option.is_empty ())
+			.collect (),
+	};
+
+	if options.is_empty () {
+		return None;
+	}
+
+	Some (options.join (" "))
+
+}
+
+pub fn filetype (
+	modeline: & str,
+) -> Option <& str> {
+
+	modeline.split (' ')
+		.filter_map (|modeline_part|
+			modeline_part.strip_prefix ("ft=")
+				.or_else (|| modeline_part.strip_prefix ("filetype=")))
+		.next_back ()
+
+}
+
+/// Apply the options which affect whitespace. The indent size comes from `sw`
+/// or `sts`, and without `ts`, so does the tab size, as that's how wide the
+/// file's indentation is. Tab stops from `vts` take precedence over the tab
+/// size, as in vim.
+pub fn apply (
+	config: & mut Config,
+	modeline: & str,
+) {
+
+	let mut tab_stop = None;
+	let mut soft_tab_stop = None;
+	let mut shift_width = None;
+
+	let positive = |value: & str| value.parse::<usize> ().ok ().filter (|value| * value > 0);
+
+	for modeline_part in modeline.split (' ') {
+
+		let (name, value) = match modeline_part.split_once ('=') {
+			Some ((name, value)) => (name, Some (value)),
+			None => (modeline_part, None),
+		};
+
+		match (name, value) {
+			("et" | "expandtab", None) => config.expand_tabs = true,
+			("noet" | "noexpandtab", None) => config.expand_tabs = false,
+			("ts" | "tabstop", Some (value)) => tab_stop = positive (value).or (tab_stop),
+			("sts" | "softtabstop", Some (value)) =>
+				soft_tab_stop = positive (value).or (soft_tab_stop),
+			("sw" | "shiftwidth", Some (value)) => shift_width = positive (value).or (shift_width),
+			("vts" | "vartabstop", Some (value)) => {
+				let tab_stops: Option <Vec <usize>> =
+					value.split (',').map (|value| value.parse ().ok ()).collect ();
+				if let Some (tab_stops) = tab_stops {
+					config.tab_stops = tab_stops;
+				}
+			},
+			("tw" | "textwidth", Some (value)) => if let Some (text_width) = positive (value) {
+				config.line_length = text_width;
+			},
+			("ff" | "fileformat", Some ("unix")) => config.line_ending = LineEnding::Lf,
+			("ff" | "fileformat", Some ("dos")) => config.line_ending = LineEnding::Crlf,
+			("ff" | "fileformat", Some ("mac")) => config.line_ending = LineEnding::Cr,
+			("fixeol" | "fixendofline", None) => config.final_newline = true,
+			("nofixeol" | "nofixendofline", None) => config.final_newline = false,
+			_ => (),
+		}
+
+	}
+
+	if let Some (indent_size) = shift_width.or (soft_tab_stop) {
+		config.indent_size = Some (indent_size);
+	}
+
+	if let Some (tab_size) = tab_stop.or (soft_tab_stop).or (shift_width) {
+		config.tab_size = tab_size;
+	}
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn finds_both_forms () {
+
+		assert_eq! (find ("x\n// vim: et ts=2\n"), Some ("et ts=2".to_owned ()));
+		assert_eq! (find ("x\n# vi:noet:sw=4\n"), Some ("noet sw=4".to_owned ()));
+		assert_eq! (find ("/* vim: set et ts=2 : */\n"), Some ("et ts=2".to_owned ()));
+		assert_eq! (find ("/* vim600: se sw=3: */\r\n"), Some ("sw=3".to_owned ()));
+		assert_eq! (find ("/* vim: set et ts=2 */\n"), None);
+		assert_eq! (find ("evim: et\n"), None);
+		assert_eq! (find ("nothing here\n"), None);
+
+	}
+
+	#[ test ]
+	fn only_searches_start_and_end () {
+
+		let mut lines = vec! ["x"; 20];
+		lines [5] = "vim: et";
+		assert_eq! (find (& lines.join ("\n")), None);
+
+		lines [4] = "vim: ts=3";
+		lines [15] = "vim: sw=2";
+		assert_eq! (find (& lines.join ("\n")), Some ("ts=3 sw=2".to_owned ()));
+
+	}
+
+	#[ test ]
+	fn finds_filetype () {
+
+		assert_eq! (filetype ("et ft=python"), Some ("python"));
+		assert_eq! (filetype ("filetype=c ft=cpp"), Some ("cpp"));
+		assert_eq! (filetype ("et"), None);
+
+	}
+
+	#[ test ]
+	fn applies_options () {
+
+		let mut config = Config::default ();
+		apply (& mut config, "et tw=72 ff=dos nofixeol");
+
+		assert! (config.expand_tabs);
+		assert_eq! (config.line_length, 72);
+		assert_eq! (config.line_ending, LineEnding::Crlf);
+		assert! (! config.final_newline);
+
+		apply (& mut config, "noet vts=4,8");
+
+		assert! (! config.expand_tabs);
+		assert_eq! (config.tab_stops, vec! [4, 8]);
+
+	}
+
+	#[ test ]
+	fn shift_width_sets_indent_size () {
+
+		let mut config = Config::default ();
+		apply (& mut config, "ts=8 sw=4");
+
+		assert_eq! (config.tab_size, 8);
+		assert_eq! (config.indent_size, Some (4));
+		assert_eq! (config.indent_size (), 4);
+
+		let mut config = Config::default ();
+		apply (& mut config, "sts=2");
+
+		assert_eq! (config.tab_size, 2);
+		assert_eq! (config.indent_size, Some (2));
+
+		let mut config = Config::default ();
+		apply (& mut config, "ts=0 ts=3");
+
+		assert_eq! (config.tab_size, 3);
+		assert_eq! (config.indent_size, None);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,70 @@
+//! Resolving paths without looking at the filesystem, so that the same file
+//! named in different ways, such as `f.txt`, `./f.txt` or `sub/../f.txt`,
+//! gives the same path.
+
+#[ cfg (feature = "fs") ]
+use std::env;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Resolve a path relative to the current directory, where there is one.
+pub fn absolute (
+	path: & Path,
+) -> PathBuf {
+
+	#[ cfg (feature = "fs") ]
+	if let Ok (current_dir) = env::current_dir () {
+		return normalise (& current_dir.join (path));
+	}
+
+	normalise (path)
+
+}
+
+/// Remove `.` and `..` components from an absolute path, without looking at
+/// the filesystem, as the file might not exist.
+pub fn normalise (
+	path: & Path,
+) -> PathBuf {
+
+	let mut normalised = PathBuf::new ();
+
+	for component in path.components () {
+		match component {
+			Component::CurDir => (),
+			Component::ParentDir => { normalised.pop (); },
+			component => normalised.push (component),
+		}
+	}
+
+	normalised
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn normalises_paths () {
+
+		assert_eq! (normalise (Path::new ("/a/./b/../c")), PathBuf::from ("/a/c"));
+		assert_eq! (normalise (Path::new ("/a/b/../../..")), PathBuf::from ("/"));
+		assert_eq! (normalise (Path::new ("/a//b/")), PathBuf::from ("/a/b"));
+
+	}
+
+	#[ test ]
+	fn absolute_paths () {
+
+		assert_eq! (absolute (Path::new ("/a/../b")), PathBuf::from ("/b"));
+		assert! (absolute (Path::new ("a/../b")).ends_with ("b"));
+		assert_eq! (absolute (Path::new ("a/../b")), absolute (Path::new ("./b")));
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
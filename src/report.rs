@@ -0,0 +1,660 @@
+//! Formatting findings for people to read.
+//!
+//! Reports can optionally use ANSI colours, and show the offending line with
+//! its whitespace made visible, since invisible characters are exactly what
+//! is hard to see in plain output. [`Statistics`] totals findings over a
+//! whole run.
+
+use crate::diff;
+use crate::diff::Change;
+use crate::fixer::CheckResult;
+use crate::fixer::Finding;
+use crate::lines;
+use crate::lines::Lines;
+
+const BOLD: & str = "\x1b[1m";
+const DIM: & str = "\x1b[2m";
+const RED: & str = "\x1b[31m";
+const GREEN: & str = "\x1b[32m";
+const YELLOW: & str = "\x1b[33m";
+const CYAN: & str = "\x1b[36m";
+const RESET: & str = "\x1b[0m";
+
+/// Number of unchanged lines shown around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// How much to report about each file.
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+pub enum Verbosity {
+
+	/// Only errors.
+	Quiet,
+
+	/// A line for each file which was fixed, and a line for each problem
+	/// which couldn't be fixed.
+	Normal,
+
+	/// A line for each line of each file with problems.
+	Verbose,
+
+}
+
+/// How findings are laid out.
+#[ derive (Clone, Copy, Debug, PartialEq, Eq) ]
+#[ cfg_attr (feature = "cli", derive (clap::ArgEnum)) ]
+pub enum Format {
+
+	/// A line for each file or problem, starting with the file name.
+	Text,
+
+	/// The file name as a heading, followed by a line for each line with
+	/// problems and a summary of the file.
+	Grouped,
+
+	/// Tab separated fields for scripts, with a row for each problem and
+	/// nothing else. The fields are the path, line, column, rule, whether
+	/// it was fixed, the message and why the rule can't fix it, and will stay
+	/// in this order.
+	Tsv,
+
+}
+
+#[ derive (Clone, Copy, Debug) ]
+pub struct Reporter {
+	pub verbosity: Verbosity,
+	pub format: Format,
+	pub colour: bool,
+	pub snippets: bool,
+	/// List each problem separately, with its column and why it can't be
+	/// fixed, eg when checking.
+	pub explain: bool,
+}
+
+impl Reporter {
+
+	fn paint (
+		& self,
+		style: & str,
+		text: & str,
+	) -> String {
+
+		if self.colour {
+			format! ("{}{}{}", style, text, RESET)
+		} else {
+			text.to_owned ()
+		}
+
+	}
+
+	/// Lines describing the findings in a file. The original contents are
+	/// used to show snippets.
+	pub fn findings (
+		& self,
+		filename: & str,
+		contents: & str,
+		findings: & [Finding],
+	) -> Vec <String> {
+
+		match (self.verbosity, self.format) {
+			(Verbosity::Quiet, _) => Vec::new (),
+			(_, Format::Grouped) => self.grouped_findings (filename, contents, findings),
+			(_, Format::Tsv) => tsv_findings (filename, findings),
+			(Verbosity::Normal, _) => self.file_findings (filename, contents, findings),
+			(Verbosity::Verbose, _) =>
+				self.line_findings (Some (filename), contents, findings.iter ()),
+		}
+
+	}
+
+	fn file_findings (
+		& self,
+		filename: & str,
+		contents: & str,
+		findings: & [Finding],
+	) -> Vec <String> {
+
+		// count the lines fixed with each message, in the order they were found
+
+		let mut fixed_counts: Vec <(& str, u64)> = Vec::new ();
+
+		for finding in findings.iter ().filter (|finding| finding.fixable) {
+			match fixed_counts.iter_mut ().find (|(message, _)| * message == finding.message) {
+				Some ((_, count)) => * count += 1,
+				None => fixed_counts.push ((& finding.message, 1)),
+			}
+		}
+
+		let mut lines = Vec::new ();
+
+		if ! fixed_counts.is_empty () {
+			lines.push (format! (
+				"{}: {}",
+				self.paint (BOLD, filename),
+				fixed_counts.iter ()
+					.map (|& (message, count)| format! (
+						"{} ({})",
+						self.paint (GREEN, message),
+						plural (count, "line")))
+					.collect::<Vec <_>> ()
+					.join (", ")));
+		}
+
+		lines.extend (self.line_findings (
+			Some (filename),
+			contents,
+			findings.iter ().filter (|finding| ! finding.fixable)));
+
+		lines
+
+	}
+
+	fn grouped_findings (
+		& self,
+		filename: & str,
+		contents: & str,
+		findings: & [Finding],
+	) -> Vec <String> {
+
+		if findings.is_empty () {
+			return Vec::new ();
+		}
+
+		let result = CheckResult::from_findings (findings);
+
+		let mut lines = vec! [ self.paint (BOLD, filename) ];
+
+		lines.extend (self.line_findings (None, contents, findings.iter ()));
+
+		lines.push (format! (
+			"  {} fixed, {}{}{}",
+			result.fixable_errors,
+			if result.unfixed_errors > 0 {
+				format! ("{}, ", plural (result.unfixed_errors, "fixable problem"))
+			} else {
+				String::new ()
+			},
+			plural (result.unfixable_errors, "unfixable problem"),
+			if result.warnings > 0 {
+				format! (", {}", plural (result.warnings, "warning"))
+			} else {
+				String::new ()
+			}));
+
+		lines.push (String::new ());
+
+		lines
+
+	}
+
+	/// A line for each line with problems, starting with the file name, or
+	/// indented under a heading if there is no file name.
+	fn line_findings <'a> (
+		& self,
+		filename: Option <& str>,
+		contents: & str,
+		findings: impl Iterator <Item = & 'a Finding>,
+	) -> Vec <String> {
+
+		let mut lines = Vec::new ();
+		let mut source_lines = Lines::new (contents).enumerate ();
+		let mut findings = findings.peekable ();
+		let indent = if filename.is_some () { "    " } else { "      " };
+
+		while let Some (finding) = findings.next () {
+
+			let line_number = finding.line_number;
+			let mut line_findings = vec! [ self.format_finding (finding) ];
+
+			// explained problems each get a line, with their column
+
+			let mut position = line_number.to_string ();
+
+			if self.explain {
+				if let Some (column) = finding.column {
+					position = format! ("{}:{}", line_number, column);
+				}
+			} else {
+				while let Some (finding) =
+						findings.next_if (|finding| finding.line_number == line_number) {
+					line_findings.push (self.format_finding (finding));
+				}
+			}
+
+			let position_text = self.paint (CYAN, & position);
+
+			lines.push (match filename {
+				Some (filename) => format! (
+					"{}:{}: {}",
+					self.paint (BOLD, filename),
+					position_text,
+					line_findings.join (", ")),
+				None => format! ("  {}: {}", position_text, line_findings.join (", ")),
+			});
+
+			if let (true, Some (reason)) = (self.explain, & finding.reason) {
+				lines.push (format! ("{}{}", indent, self.paint (DIM, reason)));
+			}
+
+			if self.snippets {
+				let source_line = source_lines
+					.find (|& (line_index, _)| line_index as u64 + 1 == line_number)
+					.map (|(_, source_line)| lines::split_line_ending (source_line).0);
+				if let Some (source_line) = source_line {
+					lines.push (format! (
+						"{}{} {}",
+						indent,
+						self.paint (DIM, "|"),
+						self.visible_whitespace (source_line)));
+				}
+			}
+
+		}
+
+		lines
+
+	}
+
+	fn format_finding (
+		& self,
+		finding: & Finding,
+	) -> String {
+
+		// fixes are described by their messages, but problems which weren't
+		// fixed are tagged with whether they could have been
+
+		let tag = match (finding.fixable, & finding.reason) {
+			(true, _) => "",
+			(false, None) => ", fixable",
+			(false, Some (_)) => ", unfixable",
+		};
+
+		format! (
+			"{} {}",
+			self.paint (
+				if finding.fixable { GREEN } else if finding.warning { YELLOW } else { RED },
+				& finding.message),
+			self.paint (DIM, & format! ("[{}{}]", finding.rule, tag)))
+
+	}
+
+	/// Show tabs as `→`, spaces at the start and end of the line as `·`, and
+	/// other invisible characters as symbols.
+	fn visible_whitespace (
+		& self,
+		content: & str,
+	) -> String {
+
+		let content_start = content.len () - content.trim_start ().len ();
+		let content_end = content.trim_end ().len ();
+
+		let mut output = String::new ();
+
+		for (index, character) in content.char_indices () {
+
+			let symbol = match character {
+				'\t' => '→',
+				' ' if index < content_start || index >= content_end => '·',
+				' ' => {
+					output.push (' ');
+					continue;
+				},
+				'\u{0}' ..= '\u{1f}' => char::from_u32 (0x2400 + character as u32).unwrap (),
+				'\u{7f}' => '␡',
+				character if character.is_whitespace () || character.is_control () => '␣',
+				'\u{200b}' | '\u{2060}' | '\u{feff}' => '␣',
+				character => {
+					output.push (character);
+					continue;
+				},
+			};
+
+			output.push_str (& self.paint (RED, & symbol.to_string ()));
+
+		}
+
+		output
+
+	}
+
+	/// A unified diff of the changes made by fixing a file, with whitespace
+	/// made visible in the changed lines.
+	pub fn diff (
+		& self,
+		filename: & str,
+		old: & str,
+		new: & str,
+	) -> Vec <String> {
+
+		let old_lines: Vec <& str> = Lines::new (old).collect ();
+		let new_lines: Vec <& str> = Lines::new (new).collect ();
+
+		// changes close enough for their context to overlap share a hunk
+
+		let mut hunks: Vec <Vec <Change>> = Vec::new ();
+
+		for change in diff::line_changes (& old_lines, & new_lines) {
+			match hunks.last_mut () {
+				Some (hunk) if change.old.start - hunk.last ().unwrap ().old.end <= DIFF_CONTEXT * 2 =>
+					hunk.push (change),
+				_ => hunks.push (vec! [ change ]),
+			}
+		}
+
+		let mut lines = vec! [
+			self.paint (BOLD, & format! ("--- {}", filename)),
+			self.paint (BOLD, & format! ("+++ {}", filename)),
+		];
+
+		for hunk in hunks {
+
+			let first = & hunk [0];
+			let last = & hunk [hunk.len () - 1];
+
+			let old_start = first.old.start.saturating_sub (DIFF_CONTEXT);
+			let old_end = (last.old.end + DIFF_CONTEXT).min (old_lines.len ());
+			let new_start = first.new.start - (first.old.start - old_start);
+			let new_end = last.new.end + (old_end - last.old.end);
+
+			let position = |start: usize, end: usize| if start == end {
+				format! ("{},0", start)
+			} else {
+				format! ("{},{}", start + 1, end - start)
+			};
+
+			lines.push (self.paint (CYAN, & format! (
+				"@@ -{} +{} @@",
+				position (old_start, old_end),
+				position (new_start, new_end))));
+
+			let mut old_index = old_start;
+
+			for change in & hunk {
+				for line in & old_lines [old_index .. change.old.start] {
+					self.diff_line (& mut lines, ' ', line);
+				}
+				for line in & old_lines [change.old.clone ()] {
+					self.diff_line (& mut lines, '-', line);
+				}
+				for line in & new_lines [change.new.clone ()] {
+					self.diff_line (& mut lines, '+', line);
+				}
+				old_index = change.old.end;
+			}
+
+			for line in & old_lines [old_index .. old_end] {
+				self.diff_line (& mut lines, ' ', line);
+			}
+
+		}
+
+		lines
+
+	}
+
+	fn diff_line (
+		& self,
+		lines: & mut Vec <String>,
+		marker: char,
+		line: & str,
+	) {
+
+		let content = line.strip_suffix ('\n').unwrap_or (line);
+
+		lines.push (match marker {
+			'-' => format! ("{}{}", self.paint (RED, "-"), self.visible_whitespace (content)),
+			'+' => format! ("{}{}", self.paint (GREEN, "+"), self.visible_whitespace (content)),
+			_ => format! ("{}{}", marker, content),
+		});
+
+		if ! line.ends_with ('\n') {
+			lines.push ("\\ No newline at end of file".to_owned ());
+		}
+
+	}
+
+	/// Closing summary, eg "312 files checked, 14 fixed, 3 unfixable problems".
+	pub fn summary (
+		& self,
+		results: & [CheckResult],
+	) -> String {
+
+		let fixed = results.iter ().filter (|result| result.fixable_errors > 0).count ();
+		let unfixed: u64 = results.iter ().map (|result| result.unfixed_errors).sum ();
+		let unfixable: u64 = results.iter ().map (|result| result.unfixable_errors).sum ();
+		let warnings: u64 = results.iter ().map (|result| result.warnings).sum ();
+
+		let mut summary = format! (
+			"{} checked, {} fixed",
+			plural (results.len () as u64, "file"),
+			fixed);
+
+		if unfixed > 0 {
+			summary.push_str (& format! (", {}", plural (unfixed, "fixable problem")));
+		}
+
+		summary.push_str (& format! (", {}", plural (unfixable, "unfixable problem")));
+
+		if warnings == 0 {
+			return summary;
+		}
+
+		format! ("{}, {}", summary, plural (warnings, "warning"))
+
+	}
+
+}
+
+/// Number of files listed in the statistics report.
+const TOP_FILES: usize = 10;
+
+/// Totals for each rule across a whole run, to see which rules are worth
+/// enforcing.
+#[ derive (Debug, Default) ]
+pub struct Statistics {
+	rules: Vec <RuleStatistics>,
+	files: Vec <(String, u64)>,
+}
+
+#[ derive (Debug) ]
+struct RuleStatistics {
+	rule: String,
+	findings: u64,
+	files: u64,
+}
+
+impl Statistics {
+
+	pub fn add (
+		& mut self,
+		filename: & str,
+		findings: & [Finding],
+	) {
+
+		if findings.is_empty () {
+			return;
+		}
+
+		self.files.push ((filename.to_owned (), findings.len () as u64));
+
+		let mut file_rules: Vec <& str> = Vec::new ();
+
+		for finding in findings {
+
+			let index = match self.rules.iter ().position (|rule| rule.rule == finding.rule) {
+				Some (index) => index,
+				None => {
+					self.rules.push (RuleStatistics {
+						rule: finding.rule.clone (),
+						findings: 0,
+						files: 0,
+					});
+					self.rules.len () - 1
+				},
+			};
+
+			let rule = & mut self.rules [index];
+			rule.findings += 1;
+
+			if ! file_rules.contains (& finding.rule.as_str ()) {
+				file_rules.push (& finding.rule);
+				rule.files += 1;
+			}
+
+		}
+
+	}
+
+	pub fn report (
+		& self,
+	) -> Vec <String> {
+
+		let mut lines = Vec::new ();
+
+		if self.rules.is_empty () {
+			lines.push ("No problems found".to_owned ());
+			return lines;
+		}
+
+		let mut rules: Vec <& RuleStatistics> = self.rules.iter ().collect ();
+		rules.sort_by (|left, right| right.findings.cmp (& left.findings)
+			.then_with (|| left.rule.cmp (& right.rule)));
+
+		let rule_width = rules.iter ().map (|rule| rule.rule.len ()).max ().unwrap_or (0);
+
+		lines.push ("Problems by rule:".to_owned ());
+
+		for rule in rules {
+			lines.push (format! (
+				"  {:width$}  {} in {}",
+				rule.rule,
+				plural (rule.findings, "problem"),
+				plural (rule.files, "file"),
+				width = rule_width));
+		}
+
+		let mut files: Vec <& (String, u64)> = self.files.iter ().collect ();
+		files.sort_by (|left, right| right.1.cmp (& left.1).then_with (|| left.0.cmp (& right.0)));
+		files.truncate (TOP_FILES);
+
+		let file_width = files.iter ().map (|(filename, _)| filename.len ()).max ().unwrap_or (0);
+
+		lines.push ("Files with the most problems:".to_owned ());
+
+		for (filename, count) in files {
+			lines.push (format! (
+				"  {:width$}  {}",
+				filename,
+				plural (* count, "problem"),
+				width = file_width));
+		}
+
+		lines
+
+	}
+
+}
+
+/// Rows of tab separated fields, with the column empty for problems which
+/// don't have one.
+fn tsv_findings (
+	filename: & str,
+	findings: & [Finding],
+) -> Vec <String> {
+
+	findings.iter ()
+		.map (|finding| [
+			tsv_field (filename),
+			finding.line_number.to_string (),
+			finding.column.map_or_else (String::new, |column| column.to_string ()),
+			tsv_field (& finding.rule),
+			finding.reason.is_none ().to_string (),
+			tsv_field (& finding.message),
+			finding.reason.as_deref ().map_or_else (String::new, tsv_field),
+			finding.fixable.to_string (),
+		].join ("\t"))
+		.collect ()
+
+}
+
+/// Escape characters which would break up the fields or rows.
+fn tsv_field (
+	value: & str,
+) -> String {
+
+	value
+		.replace ('\\', "\\\\")
+		.replace ('\t', "\\t")
+		.replace ('\n', "\\n")
+		.replace ('\r', "\\r")
+
+}
+
+/// A count with a noun, eg "1 file" or "2 files".
+pub fn plural (
+	count: u64,
+	noun: & str,
+) -> String {
+
+	if count == 1 {
+		format! ("{} {}", count, noun)
+	} else {
+		format! ("{} {}s", count, noun)
+	}
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use std::borrow::Cow;
+
+	use super::*;
+
+	fn finding (
+		rule: & str,
+		column: Option <usize>,
+		fixable: bool,
+		reason: Option <& 'static str>,
+	) -> Finding {
+
+		Finding {
+			line_number: 3,
+			column,
+			rule: rule.to_owned (),
+			message: Cow::Borrowed ("a\tmessage"),
+			fixable,
+			reason: reason.map (Cow::Borrowed),
+			warning: false,
+		}
+
+	}
+
+	#[ test ]
+	fn tsv_fields () {
+
+		let findings = [
+			finding ("trailing-whitespace", Some (2), true, None),
+			finding ("trailing-whitespace", Some (2), false, None),
+			finding ("line-length", None, false, Some ("can't\nfix")),
+		];
+
+		assert_eq! (tsv_findings ("dir\\a b.txt", & findings), vec! [
+			"dir\\\\a b.txt\t3\t2\ttrailing-whitespace\ttrue\ta\\tmessage\t\ttrue",
+			"dir\\\\a b.txt\t3\t2\ttrailing-whitespace\ttrue\ta\\tmessage\t\tfalse",
+			"dir\\\\a b.txt\t3\t\tline-length\tfalse\ta\\tmessage\tcan't\\nfix\tfalse",
+		]);
+
+	}
+
+	#[ test ]
+	fn plurals () {
+
+		assert_eq! (plural (0, "file"), "0 files");
+		assert_eq! (plural (1, "file"), "1 file");
+		assert_eq! (plural (2, "problem"), "2 problems");
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,2117 @@
+//! Rules which detect and fix problems, one line at a time.
+//!
+//! Each rule looks at a line with [`Rule::check`], and if it finds a problem
+//! which it is able to fix, [`Rule::fix`] is called straight away so that
+//! later rules see the corrected line. The same sequence runs whether or not
+//! the output is kept, so checking and fixing always report the same
+//! findings.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::borrow::Cow;
+
+use crate::config::Config;
+use crate::config::ControlCharacters;
+use crate::config::FormFeeds;
+use crate::config::LineEnding;
+use crate::config::MarkdownLineBreaks;
+use crate::config::Severity;
+use crate::config::UnicodeWhitespace;
+use crate::config::VerticalTabs;
+use crate::fixer::Finding;
+use crate::indent;
+use crate::lines;
+use crate::lines::LineEndingStats;
+use crate::lines::Lines;
+use crate::width;
+use crate::width::TabStops;
+
+/// A single line, split from its line ending, as it is being fixed.
+pub struct Line <'a> {
+	pub number: u64,
+	pub content: Cow <'a, str>,
+	pub ending: & 'a str,
+	pub removed: bool,
+}
+
+pub trait Rule {
+
+	/// Stable identifier, eg `trailing-whitespace`.
+	fn id (& self) -> & str;
+
+	/// Short explanation of what the rule looks for.
+	fn description (& self) -> & str;
+
+	/// Whether problems found by this rule can be fixed, with these settings.
+	fn fixable (& self, config: & Config) -> bool;
+
+	/// Why problems found by this rule can't be fixed, with these settings.
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"the rule can't fix the problems it finds"
+	}
+
+	/// Called before the first line of each file.
+	fn start_file (
+		& mut self,
+		_config: & Config,
+		_contents: & str,
+	) {
+	}
+
+	/// Look for a problem on a line, returning a message describing it.
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>>;
+
+	/// Column where the problem found by the last call to `check` starts,
+	/// counting characters from one, for problems with a position.
+	fn column (
+		& self,
+		_config: & Config,
+		_line: & Line,
+	) -> Option <usize> {
+		None
+	}
+
+	/// Fix the problem found by the last call to `check`.
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	);
+
+}
+
+/// An ordered set of rules which are run over each line in turn.
+pub struct Registry {
+	rules: Vec <Box <dyn Rule>>,
+}
+
+impl Registry {
+
+	pub fn new () -> Registry {
+		Registry { rules: Vec::new () }
+	}
+
+	/// All the built in rules, in the order they are applied.
+	pub fn builtin () -> Registry {
+
+		let mut registry = Registry::new ();
+		registry.add_builtin ();
+
+		registry
+
+	}
+
+	/// Add the built in rules after any already added, so that custom rules
+	/// run first and the built in rules clean up after them.
+	pub fn add_builtin (
+		& mut self,
+	) {
+
+		self.add (BlankLinesRule::default ());
+		self.add (MixedLineEndingsRule::default ());
+		self.add (LineEndingRule);
+		self.add (FinalNewlineRule::default ());
+		self.add (ControlCharactersRule);
+		self.add (EscapeCharactersRule);
+		self.add (VerticalTabsRule);
+		self.add (FormFeedsRule);
+		self.add (SmartTabsIndentRule);
+		self.add (SmartTabsAlignRule);
+		self.add (MixedIndentationRule);
+		self.add (TabExpansionRule);
+		self.add (IndentSizeRule::default ());
+		self.add (TabsAfterCharactersRule);
+		self.add (UnicodeWhitespaceRule);
+		self.add (MarkdownLineBreaksRule);
+		self.add (TrailingWhitespaceRule);
+		self.add (LineWrapRule::default ());
+		self.add (LineLengthRule);
+
+	}
+
+	pub fn add (
+		& mut self,
+		rule: impl Rule + 'static,
+	) {
+		self.rules.push (Box::new (rule));
+	}
+
+	pub fn rules (
+		& self,
+	) -> impl Iterator <Item = & dyn Rule> {
+		self.rules.iter ().map (|rule| rule.as_ref ())
+	}
+
+	/// Run every rule over some text, returning the fixed text along with
+	/// everything that was found.
+	pub fn run (
+		& mut self,
+		config: & Config,
+		contents: & str,
+	) -> (String, Vec <Finding>) {
+
+		let config = & with_line_ending (config, contents);
+
+		let mut output = String::with_capacity (contents.len ());
+		let mut findings = Vec::new ();
+
+		let mut rules: Vec <& mut Box <dyn Rule>> =
+			self.rules.iter_mut ()
+				.filter (|rule| config.is_rule_enabled (rule.id ()))
+				.collect ();
+
+		for rule in & mut rules {
+			rule.start_file (config, contents);
+		}
+
+		for (line_index, source_line) in Lines::new (contents).enumerate () {
+
+			let (content, ending) = lines::split_line_ending (source_line);
+
+			let mut line = Line {
+				number: line_index as u64 + 1,
+				content: Cow::Borrowed (content),
+				ending,
+				removed: false,
+			};
+
+			let ignored = config.is_line_ignored (content);
+
+			// columns are for the original line, so each fix's changes are
+			// followed back to the characters they came from
+
+			let mut origins: Option <Vec <usize>> = None;
+			let original_len = content.chars ().count ();
+
+			for rule in & mut rules {
+
+				if line.removed {
+					break;
+				}
+
+				let message = match rule.check (config, & line) {
+					Some (message) => message,
+					None => continue,
+				};
+
+				// rules still see lines outside the ranges and ignored lines, to
+				// keep track of blank lines and so on, but they are left alone
+
+				if ignored || ! config.line_ranges.as_ref ()
+						.is_none_or (|line_ranges| line_ranges.contains (line.number)) {
+					continue;
+				}
+
+				let fixable = rule.fixable (config);
+
+				findings.push (Finding {
+					line_number: line.number,
+					column: rule.column (config, & line).map (|column|
+						original_index (origins.as_deref (), original_len, column - 1) + 1),
+					rule: rule.id ().to_owned (),
+					message,
+					fixable,
+					reason: (! fixable)
+						.then (|| Cow::Owned (rule.unfixable_reason (config).to_owned ())),
+					warning: config.rule_severity (rule.id ()) == Severity::Warning,
+				});
+
+				if fixable {
+
+					let before: Vec <char> = line.content.chars ().collect ();
+
+					rule.fix (config, & mut line);
+
+					let after: Vec <char> = line.content.chars ().collect ();
+
+					if after != before {
+						origins = Some (
+							align_characters (& before, & after).into_iter ()
+								.map (|index| original_index (origins.as_deref (), original_len, index))
+								.collect ());
+					}
+
+				}
+
+			}
+
+			if ! line.removed {
+				output.push_str (& line.content);
+				output.push_str (line.ending);
+			}
+
+		}
+
+		(output, findings)
+
+	}
+
+}
+
+/// Largest number of pairs of changed characters compared when aligning a
+/// line before and after a fix, beyond which they are treated as replaced.
+const MAX_ALIGN_PAIRS: usize = 1 << 18;
+
+/// Index in the original line of a character in the fixed line, given where
+/// each character came from, or the same index if nothing has changed.
+/// Indexes past the end of the line stay past the end.
+fn original_index (
+	origins: Option <& [usize]>,
+	original_len: usize,
+	index: usize,
+) -> usize {
+
+	match origins {
+		Some (origins) => origins.get (index).copied ()
+			.unwrap_or_else (|| original_len + index - origins.len ()),
+		None => index,
+	}
+
+}
+
+/// For each character of a line after a fix, the index of the character it
+/// came from before the fix, or which it replaced or was inserted before.
+fn align_characters (
+	before: & [char],
+	after: & [char],
+) -> Vec <usize> {
+
+	let prefix = before.iter ().zip (after)
+		.take_while (|(before, after)| before == after)
+		.count ();
+
+	let suffix = before [prefix .. ].iter ().rev ()
+		.zip (after [prefix .. ].iter ().rev ())
+		.take_while (|(before, after)| before == after)
+		.count ();
+
+	let mut origins: Vec <usize> = (0 .. prefix).collect ();
+
+	origins.extend (
+		align_changed (
+			& before [prefix .. before.len () - suffix],
+			& after [prefix .. after.len () - suffix])
+		.into_iter ()
+		.map (|index| prefix + index));
+
+	origins.extend (before.len () - suffix .. before.len ());
+
+	origins
+
+}
+
+/// Match up the characters which changed using their longest common
+/// subsequence, so that eg each expanded tab is found separately.
+fn align_changed (
+	before: & [char],
+	after: & [char],
+) -> Vec <usize> {
+
+	if before.len () * after.len () > MAX_ALIGN_PAIRS {
+		return vec! [0; after.len ()];
+	}
+
+	// lengths of the common subsequences of every pair of suffixes
+
+	let width = after.len () + 1;
+	let mut lengths = vec! [0; (before.len () + 1) * width];
+
+	for before_index in (0 .. before.len ()).rev () {
+		for after_index in (0 .. after.len ()).rev () {
+			lengths [before_index * width + after_index] =
+				if before [before_index] == after [after_index] {
+					lengths [(before_index + 1) * width + after_index + 1] + 1
+				} else {
+					lengths [(before_index + 1) * width + after_index]
+						.max (lengths [before_index * width + after_index + 1])
+				};
+		}
+	}
+
+	// characters which were inserted, or replaced others, come from the next
+	// character which wasn't kept
+
+	let mut origins = Vec::with_capacity (after.len ());
+	let mut before_index = 0;
+
+	for after_index in 0 .. after.len () {
+
+		while before_index < before.len ()
+				&& before [before_index] != after [after_index]
+				&& lengths [(before_index + 1) * width + after_index]
+					> lengths [before_index * width + after_index + 1] {
+			before_index += 1;
+		}
+
+		origins.push (before_index);
+
+		if before_index < before.len () && before [before_index] == after [after_index] {
+			before_index += 1;
+		}
+
+	}
+
+	origins
+
+}
+
+fn with_line_ending <'a> (
+	config: & 'a Config,
+	contents: & str,
+) -> Cow <'a, Config> {
+
+	// when keeping line endings, a file with mixed line endings is fixed to
+	// whichever is most common
+
+	if config.line_ending != LineEnding::Keep {
+		return Cow::Borrowed (config);
+	}
+
+	let line_ending_stats = LineEndingStats::new (contents);
+
+	if ! line_ending_stats.is_mixed () {
+		return Cow::Borrowed (config);
+	}
+
+	let mut config = config.clone ();
+	config.line_ending = line_ending_stats.dominant ();
+
+	Cow::Owned (config)
+
+}
+
+impl Default for Registry {
+
+	fn default () -> Registry {
+		Registry::builtin ()
+	}
+
+}
+
+#[ derive (Default) ]
+struct BlankLinesRule {
+	blank_lines: usize,
+}
+
+impl Rule for BlankLinesRule {
+
+	fn id (& self) -> & str { "blank-lines" }
+	fn description (& self) -> & str { "More consecutive blank lines than allowed" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn start_file (
+		& mut self,
+		_config: & Config,
+		_contents: & str,
+	) {
+		self.blank_lines = 0;
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! line.content.trim ().is_empty () {
+			self.blank_lines = 0;
+			return None;
+		}
+
+		self.blank_lines += 1;
+
+		if self.blank_lines <= config.max_blank_lines ? {
+			return None;
+		}
+
+		Some ("removed excess blank line".into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		line: & mut Line,
+	) {
+		line.removed = true;
+	}
+
+}
+
+#[ derive (Default) ]
+struct MixedLineEndingsRule {
+	message: Option <(u64, String)>,
+}
+
+impl Rule for MixedLineEndingsRule {
+
+	fn id (& self) -> & str { "mixed-line-endings" }
+	fn description (& self) -> & str { "File uses more than one style of line ending" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn start_file (
+		& mut self,
+		config: & Config,
+		contents: & str,
+	) {
+
+		let line_ending_stats = LineEndingStats::new (contents);
+
+		self.message = if line_ending_stats.is_mixed () {
+			line_ending_stats.first_mismatch (config.line_ending)
+				.map (|first_mismatch| (
+					first_mismatch + 1,
+					format! ("mixed line endings ({})", line_ending_stats.summary ()),
+				))
+		} else {
+			None
+		};
+
+	}
+
+	fn check (
+		& mut self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		match self.message {
+			Some ((line_number, ref message)) if line_number == line.number =>
+				Some (message.clone ().into ()),
+			_ => None,
+		}
+
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		_line: & mut Line,
+	) {
+		// the line ending rule fixes each line individually
+	}
+
+}
+
+struct LineEndingRule;
+
+impl Rule for LineEndingRule {
+
+	fn id (& self) -> & str { "line-ending" }
+	fn description (& self) -> & str { "Line ending differs from the configured style" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		let target_ending = config.line_ending.target () ?;
+
+		if line.ending.is_empty () || line.ending == target_ending {
+			return None;
+		}
+
+		Some (match line.ending {
+			"\r" => "fixed mac line ending".into (),
+			"\r\n" => "fixed windows line ending".into (),
+			_ => "fixed unix line ending".into (),
+		})
+
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		if let Some (target_ending) = config.line_ending.target () {
+			line.ending = target_ending;
+		}
+
+	}
+
+}
+
+/// Adds a line ending to the last line, the same as the other lines have.
+#[ derive (Default) ]
+struct FinalNewlineRule {
+	ending: & 'static str,
+}
+
+impl Rule for FinalNewlineRule {
+
+	fn id (& self) -> & str { "final-newline" }
+	fn description (& self) -> & str { "No line ending on the last line, when one is required" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn start_file (
+		& mut self,
+		config: & Config,
+		_contents: & str,
+	) {
+		self.ending = config.line_ending.target ().unwrap_or ("\n");
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		match line.ending {
+			"\r\n" => self.ending = "\r\n",
+			"\r" => self.ending = "\r",
+			"\n" => self.ending = "\n",
+			_ => (),
+		}
+
+		if ! config.final_newline || ! line.ending.is_empty () || line.content.is_empty () {
+			return None;
+		}
+
+		Some ("added line ending at end of file".into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		line: & mut Line,
+	) {
+		line.ending = self.ending;
+	}
+
+}
+
+fn is_zero_width_space (
+	character: char,
+) -> bool {
+	matches! (character, '\u{200b}' | '\u{2060}')
+}
+
+fn is_unicode_whitespace (
+	character: char,
+) -> bool {
+	is_zero_width_space (character)
+		|| (character.is_whitespace () && ! character.is_ascii ())
+}
+
+/// Form feeds and vertical tabs have their own rules.
+fn is_control_character (
+	character: char,
+) -> bool {
+	character.is_control ()
+		&& ! matches! (character, '\t' | '\n' | '\r' | '\u{b}' | '\u{c}')
+		&& ! is_unicode_whitespace (character)
+}
+
+/// Whitespace removed from the end of lines, which leaves form feeds and
+/// vertical tabs to their own rules.
+fn is_trailing_whitespace (
+	character: char,
+) -> bool {
+	character.is_whitespace ()
+		&& ! matches! (character, '\n' | '\r' | '\u{b}' | '\u{c}')
+}
+
+fn is_stripable_control_character (
+	character: char,
+) -> bool {
+	is_control_character (character) && character != '\u{1b}'
+}
+
+fn describe_control_characters (
+	content: & str,
+	predicate: fn (char) -> bool,
+) -> Option <String> {
+
+	let mut control_characters =
+		content.chars ()
+			.enumerate ()
+			.filter (|& (_, character)| predicate (character));
+
+	let (column, character) = control_characters.next () ?;
+	let count = control_characters.count () + 1;
+
+	Some (if count == 1 {
+		format! (
+			"control character U+{:04X} at column {}",
+			character as u32,
+			column + 1)
+	} else {
+		format! (
+			"{} control characters, first U+{:04X} at column {}",
+			count,
+			character as u32,
+			column + 1)
+	})
+
+}
+
+/// Column of the first character matching a predicate, counting from one.
+fn find_column (
+	content: & str,
+	predicate: impl Fn (char) -> bool,
+) -> Option <usize> {
+	content.chars ().position (predicate).map (|index| index + 1)
+}
+
+struct ControlCharactersRule;
+
+impl Rule for ControlCharactersRule {
+
+	fn id (& self) -> & str { "control-characters" }
+	fn description (& self) -> & str { "Control characters such as NUL or backspace" }
+
+	fn fixable (
+		& self,
+		config: & Config,
+	) -> bool {
+		config.control_characters != ControlCharacters::Report
+	}
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"control characters are only removed with --control-characters strip or replace"
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! line.content.contains (is_stripable_control_character) {
+			return None;
+		}
+
+		Some (match config.control_characters {
+			ControlCharacters::Report =>
+				describe_control_characters (
+					& line.content,
+					is_stripable_control_character) ?.into (),
+			ControlCharacters::Strip => "stripped control characters".into (),
+			ControlCharacters::Replace => "replaced control characters".into (),
+		})
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+		find_column (& line.content, is_stripable_control_character)
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		line.content = Cow::Owned (
+			line.content.chars ()
+				.filter_map (|character| match character {
+					character if ! is_stripable_control_character (character) =>
+						Some (character),
+					_ if config.control_characters == ControlCharacters::Replace =>
+						Some (' '),
+					_ => None,
+				})
+				.collect ());
+
+	}
+
+}
+
+struct EscapeCharactersRule;
+
+impl Rule for EscapeCharactersRule {
+
+	fn id (& self) -> & str { "escape-characters" }
+	fn description (& self) -> & str { "Escape characters, which can't be removed safely" }
+	fn fixable (& self, _config: & Config) -> bool { false }
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"escape characters are usually part of terminal escape sequences, which removing \
+			them would break"
+	}
+
+	fn check (
+		& mut self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		describe_control_characters (
+			& line.content,
+			|character| character == '\u{1b}',
+		).map (Cow::Owned)
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+		find_column (& line.content, |character| character == '\u{1b}')
+	}
+
+	fn fix (& mut self, _config: & Config, _line: & mut Line) {}
+
+}
+
+fn describe_characters (
+	content: & str,
+	target: char,
+	name: & str,
+) -> Option <String> {
+
+	let mut columns =
+		content.chars ()
+			.enumerate ()
+			.filter (|& (_, character)| character == target)
+			.map (|(column, _)| column + 1);
+
+	let column = columns.next () ?;
+	let count = columns.count () + 1;
+
+	Some (if count == 1 {
+		format! ("{} at column {}", name, column)
+	} else {
+		format! ("{} {}s, first at column {}", count, name, column)
+	})
+
+}
+
+/// Form feeds, which old sources use to separate pages but which confuse
+/// other tools. Replacing them with blank lines keeps the separation.
+struct FormFeedsRule;
+
+impl Rule for FormFeedsRule {
+
+	fn id (& self) -> & str { "form-feeds" }
+	fn description (& self) -> & str { "Form feed characters, used as page separators" }
+
+	fn fixable (
+		& self,
+		config: & Config,
+	) -> bool {
+		config.form_feeds != FormFeeds::Report
+	}
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"form feeds are only removed with --form-feeds remove or blank-line"
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		Some (match config.form_feeds {
+			FormFeeds::Report => describe_characters (& line.content, '\u{c}', "form feed") ?.into (),
+			_ if ! line.content.contains ('\u{c}') => return None,
+			FormFeeds::Remove => "removed form feeds".into (),
+			FormFeeds::BlankLine => "replaced form feeds with blank lines".into (),
+		})
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+		find_column (& line.content, |character| character == '\u{c}')
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		if config.form_feeds == FormFeeds::Remove {
+			line.content = Cow::Owned (line.content.replace ('\u{c}', ""));
+			return;
+		}
+
+		// each form feed becomes a blank line, between the text before and
+		// after it
+
+		let mut parts = Vec::new ();
+
+		for (index, part) in line.content.split ('\u{c}').enumerate () {
+			if index > 0 {
+				parts.push ("");
+			}
+			if ! part.is_empty () {
+				parts.push (part);
+			}
+		}
+
+		let ending = if line.ending.is_empty () { "\n" } else { line.ending };
+
+		line.content = Cow::Owned (parts.join (ending));
+
+	}
+
+}
+
+struct VerticalTabsRule;
+
+impl Rule for VerticalTabsRule {
+
+	fn id (& self) -> & str { "vertical-tabs" }
+	fn description (& self) -> & str { "Vertical tab characters" }
+
+	fn fixable (
+		& self,
+		config: & Config,
+	) -> bool {
+		config.vertical_tabs != VerticalTabs::Report
+	}
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"vertical tabs are only removed with --vertical-tabs remove"
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		Some (match config.vertical_tabs {
+			VerticalTabs::Report =>
+				describe_characters (& line.content, '\u{b}', "vertical tab") ?.into (),
+			_ if ! line.content.contains ('\u{b}') => return None,
+			VerticalTabs::Remove => "removed vertical tabs".into (),
+		})
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+		find_column (& line.content, |character| character == '\u{b}')
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		line: & mut Line,
+	) {
+		line.content = Cow::Owned (line.content.replace ('\u{b}', ""));
+	}
+
+}
+
+struct SmartTabsIndentRule;
+
+impl Rule for SmartTabsIndentRule {
+
+	fn id (& self) -> & str { "smart-tabs-indent" }
+	fn description (& self) -> & str { "Indentation which could use tabs, in smart tabs mode" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! config.smart_tabs || config.expand_tabs {
+			return None;
+		}
+
+		indent::indent_with_tabs (& line.content, & config.tab_stops ()) ?;
+
+		Some ("indented with tabs".into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		if let Some (fixed_content) =
+				indent::indent_with_tabs (& line.content, & config.tab_stops ()) {
+			line.content = Cow::Owned (fixed_content);
+		}
+
+	}
+
+}
+
+struct SmartTabsAlignRule;
+
+impl Rule for SmartTabsAlignRule {
+
+	fn id (& self) -> & str { "smart-tabs-align" }
+	fn description (& self) -> & str { "Tabs after the indentation, in smart tabs mode" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! config.smart_tabs || config.expand_tabs {
+			return None;
+		}
+
+		indent::align_with_spaces (& line.content, & config.tab_stops ()) ?;
+
+		Some ("aligned with spaces".into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		if let Some (fixed_content) =
+				indent::align_with_spaces (& line.content, & config.tab_stops ()) {
+			line.content = Cow::Owned (fixed_content);
+		}
+
+	}
+
+}
+
+struct MixedIndentationRule;
+
+impl Rule for MixedIndentationRule {
+
+	fn id (& self) -> & str { "mixed-indentation" }
+	fn description (& self) -> & str { "Indentation which mixes tabs and spaces" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if config.smart_tabs && ! config.expand_tabs {
+			return None;
+		}
+
+		indent::fix_mixed (& line.content, ! config.expand_tabs, & config.tab_stops ()) ?;
+
+		Some ("fixed mixed indentation".into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		if let Some (fixed_content) =
+				indent::fix_mixed (& line.content, ! config.expand_tabs, & config.tab_stops ()) {
+			line.content = Cow::Owned (fixed_content);
+		}
+
+	}
+
+}
+
+struct TabExpansionRule;
+
+impl Rule for TabExpansionRule {
+
+	fn id (& self) -> & str { "tab-expansion" }
+	fn description (& self) -> & str { "Tabs, when tabs are expanded into spaces" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! config.expand_tabs || ! line.content.contains ('\t') {
+			return None;
+		}
+
+		Some ("expanded tabs".into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+		line.content = Cow::Owned (
+			width::expand_tabs (& line.content, 0, & config.tab_stops ()));
+	}
+
+}
+
+/// Indentation which is between levels, which is rounded to the nearest
+/// level when asked. Alignment after tabs is left alone in smart tabs mode.
+#[ derive (Default) ]
+struct IndentSizeRule {
+	rounded: Option <(usize, usize, String)>,
+}
+
+impl Rule for IndentSizeRule {
+
+	fn id (& self) -> & str { "indent-size" }
+	fn description (& self) -> & str { "Indentation between levels, when normalising it" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! config.normalise_indent || config.smart_tabs {
+			return None;
+		}
+
+		self.rounded = indent::round_indent (
+			& line.content,
+			config.indent_size (),
+			! config.expand_tabs,
+			& config.tab_stops ());
+
+		let (indent_width, rounded_width, _) = self.rounded.as_ref () ?;
+
+		Some (format! (
+			"indented to column {} instead of {}",
+			rounded_width + 1,
+			indent_width + 1).into ())
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+		let (indent, _) = indent::split_indent (& line.content);
+		Some (indent.chars ().count () + 1)
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		line: & mut Line,
+	) {
+
+		if let Some ((_, _, content)) = self.rounded.take () {
+			line.content = Cow::Owned (content);
+		}
+
+	}
+
+}
+
+struct TabsAfterCharactersRule;
+
+impl Rule for TabsAfterCharactersRule {
+
+	fn id (& self) -> & str { "tabs-after-characters" }
+	fn description (& self) -> & str { "Tabs after other characters, when tabs are kept" }
+	fn fixable (& self, _config: & Config) -> bool { false }
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"tabs are kept, and spaces would only line up the same for one tab size"
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if config.expand_tabs || config.smart_tabs {
+			return None;
+		}
+
+		if ! line.content.chars ()
+				.skip_while (|character| * character == '\t')
+				.any (|character| character == '\t') {
+			return None;
+		}
+
+		Some ("tabs after other characters".into ())
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+
+		let indent = line.content.chars ().take_while (|& character| character == '\t').count ();
+
+		find_column (& line.content [indent .. ], |character| character == '\t')
+			.map (|column| indent + column)
+
+	}
+
+	fn fix (& mut self, _config: & Config, _line: & mut Line) {}
+
+}
+
+struct UnicodeWhitespaceRule;
+
+impl Rule for UnicodeWhitespaceRule {
+
+	fn id (& self) -> & str { "unicode-whitespace" }
+	fn description (& self) -> & str { "Non-ASCII whitespace such as non-breaking spaces" }
+
+	fn fixable (
+		& self,
+		config: & Config,
+	) -> bool {
+		config.unicode_whitespace != UnicodeWhitespace::Report
+	}
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"unicode whitespace is only changed with --unicode-whitespace replace or remove"
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! line.content.contains (is_unicode_whitespace) {
+			return None;
+		}
+
+		Some (match config.unicode_whitespace {
+			UnicodeWhitespace::Report => "unicode whitespace".into (),
+			UnicodeWhitespace::Replace => "replaced unicode whitespace".into (),
+			UnicodeWhitespace::Remove => "removed unicode whitespace".into (),
+		})
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+		find_column (& line.content, is_unicode_whitespace)
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		line.content = Cow::Owned (
+			line.content.chars ()
+				.filter_map (|character| match character {
+					character if ! is_unicode_whitespace (character) => Some (character),
+					_ if config.unicode_whitespace == UnicodeWhitespace::Remove => None,
+					character if is_zero_width_space (character) => None,
+					_ => Some (' '),
+				})
+				.collect ());
+
+	}
+
+}
+
+fn is_markdown_line_break (
+	config: & Config,
+	content: & str,
+) -> bool {
+
+	config.filetype.as_deref () == Some ("markdown")
+		&& config.markdown_line_breaks != MarkdownLineBreaks::Remove
+		&& content.ends_with ("  ")
+		&& ! content [ .. content.len () - 2].ends_with (char::is_whitespace)
+		&& ! content.trim ().is_empty ()
+
+}
+
+struct MarkdownLineBreaksRule;
+
+impl Rule for MarkdownLineBreaksRule {
+
+	fn id (& self) -> & str { "markdown-line-breaks" }
+	fn description (& self) -> & str { "Two space line breaks in markdown, when converting to backslashes" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if config.markdown_line_breaks != MarkdownLineBreaks::Backslash
+				|| ! is_markdown_line_break (config, & line.content) {
+			return None;
+		}
+
+		Some ("converted line break to backslash".into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		line: & mut Line,
+	) {
+		line.content = Cow::Owned (
+			format! ("{}\\", line.content.trim_end ()));
+	}
+
+}
+
+struct TrailingWhitespaceRule;
+
+impl Rule for TrailingWhitespaceRule {
+
+	fn id (& self) -> & str { "trailing-whitespace" }
+	fn description (& self) -> & str { "Whitespace at the end of a line" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		// lines which were split by earlier rules are checked part by part
+
+		Lines::new (& line.content)
+			.map (|part| lines::split_line_ending (part).0)
+			.any (|part| part.ends_with (is_trailing_whitespace)
+				&& ! is_markdown_line_break (config, part))
+			.then_some ("removed whitespace from end".into ())
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+
+		let content = lines::split_line_ending (Lines::new (& line.content).next () ?).0;
+
+		Some (content.trim_end_matches (is_trailing_whitespace).chars ().count () + 1)
+
+	}
+
+	fn fix (
+		& mut self,
+		config: & Config,
+		line: & mut Line,
+	) {
+
+		line.content = Cow::Owned (
+			Lines::new (& line.content)
+				.map (|part| match lines::split_line_ending (part) {
+					(content, _) if is_markdown_line_break (config, content) => part.to_owned (),
+					(content, ending) =>
+						format! ("{}{}", content.trim_end_matches (is_trailing_whitespace), ending),
+				})
+				.collect ());
+
+	}
+
+}
+
+fn is_unbreakable (
+	content: & str,
+	line_length: usize,
+	tab_stops: & TabStops,
+) -> bool {
+
+	let mut column = 0;
+
+	for character in content.chars () {
+
+		if column >= line_length && character.is_whitespace () {
+			return false;
+		}
+
+		column = if character == '\t' {
+			width::next_tab_stop (column, tab_stops)
+		} else {
+			column + width::char_width (character)
+		};
+
+	}
+
+	true
+
+}
+
+/// Wraps long lines of prose at word boundaries. Fenced code blocks,
+/// indented code, headings and tables are left alone, and list items and
+/// block quotes keep their indentation on the lines which are added.
+#[ derive (Default) ]
+struct LineWrapRule {
+	in_fence: bool,
+	wrapped: Vec <String>,
+}
+
+impl Rule for LineWrapRule {
+
+	fn id (& self) -> & str { "line-wrap" }
+	fn description (& self) -> & str { "Long lines of prose, when wrapping is enabled" }
+	fn fixable (& self, _config: & Config) -> bool { true }
+
+	fn start_file (
+		& mut self,
+		_config: & Config,
+		_contents: & str,
+	) {
+		self.in_fence = false;
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		let content = & line.content;
+		let trimmed = content.trim_start ();
+
+		if trimmed.starts_with ("```") || trimmed.starts_with ("~~~") {
+			self.in_fence = ! self.in_fence;
+			return None;
+		}
+
+		// wrapping code could break it, eg inside a string, so only file types
+		// which are known to be prose are wrapped
+
+		let is_prose = config.filetype.as_ref ().is_some_and (|filetype|
+			config.line_length_options.wrap_filetypes.contains (filetype));
+
+		if ! config.line_length_options.wrap || ! is_prose || self.in_fence {
+			return None;
+		}
+
+		let content_width = width::line_width (content, & config.tab_stops ());
+
+		if content_width <= config.line_length {
+			return None;
+		}
+
+		self.wrapped = wrap_line (content, config.line_length, & config.tab_stops ()) ?;
+
+		Some (format! (
+			"wrapped long line ({} columns, maximum {})",
+			content_width,
+			config.line_length).into ())
+
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		line: & mut Line,
+	) {
+
+		let ending = if line.ending.is_empty () { "\n" } else { line.ending };
+
+		line.content = Cow::Owned (self.wrapped.join (ending));
+
+	}
+
+}
+
+/// Split a line at word boundaries so each part fits, or return `None` if
+/// it isn't prose or can't be split.
+fn wrap_line (
+	content: & str,
+	line_length: usize,
+	tab_stops: & TabStops,
+) -> Option <Vec <String>> {
+
+	lazy_static! {
+		static ref PREFIX_REGEX: Regex =
+			Regex::new (r"^([ \t]*(?:>[ \t]?)*)((?:[-*+]|[0-9]+[.)])[ \t]+)?").unwrap ();
+	}
+
+	let captures = PREFIX_REGEX.captures (content) ?;
+	let quote_prefix = captures.get (1).map_or ("", |prefix| prefix.as_str ());
+	let list_marker = captures.get (2).map_or ("", |marker| marker.as_str ());
+	let first_prefix = & content [ .. captures [0].len ()];
+	let body = & content [first_prefix.len () .. ];
+
+	// indented code, headings and tables would be broken by wrapping
+
+	let indent_width = width::line_width (quote_prefix, tab_stops);
+
+	if (list_marker.is_empty () && indent_width >= 4 && ! quote_prefix.contains ('>'))
+			|| body.starts_with ('#')
+			|| body.starts_with ('|')
+			|| body.trim ().is_empty () {
+		return None;
+	}
+
+	let continuation_prefix = format! (
+		"{}{}",
+		quote_prefix,
+		" ".repeat (width::line_width (list_marker, tab_stops)));
+
+	// keep trailing whitespace, which can be a markdown line break
+
+	let trailing = & body [body.trim_end ().len () .. ];
+
+	let mut parts = Vec::new ();
+	let mut current = first_prefix.to_owned ();
+	let mut current_empty = true;
+
+	for word in body.split_whitespace () {
+
+		if current_empty {
+			current.push_str (word);
+			current_empty = false;
+			continue;
+		}
+
+		let candidate = format! ("{} {}", current, word);
+
+		if width::line_width (& candidate, tab_stops) <= line_length {
+			current = candidate;
+		} else {
+			parts.push (current);
+			current = format! ("{}{}", continuation_prefix, word);
+		}
+
+	}
+
+	current.push_str (trailing);
+	parts.push (current);
+
+	if parts.len () < 2 {
+		return None;
+	}
+
+	Some (parts)
+
+}
+
+struct LineLengthRule;
+
+impl Rule for LineLengthRule {
+
+	fn id (& self) -> & str { "line-length" }
+	fn description (& self) -> & str { "Lines longer than the maximum line length" }
+	fn fixable (& self, _config: & Config) -> bool { false }
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"long lines can't be shortened safely in general, apart from prose with \
+			--line-length-wrap"
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		lazy_static! {
+			static ref URL_REGEX: Regex =
+				Regex::new (r"[a-zA-Z][a-zA-Z0-9+.-]*://\S").unwrap ();
+		}
+
+		// lines which were wrapped are checked part by part, split the same
+		// way as the file itself so any line ending is excluded from the width
+
+		let tab_stops = config.tab_stops ();
+
+		let (content_width, content) = Lines::new (& line.content)
+			.map (|part| lines::split_line_ending (part).0)
+			.map (|part| (width::line_width (part, & tab_stops), part))
+			.max_by_key (|& (part_width, _)| part_width) ?;
+
+		if content_width <= config.line_length {
+			return None;
+		}
+
+		let options = & config.line_length_options;
+
+		if options.ignore_urls && URL_REGEX.is_match (content) {
+			return None;
+		}
+
+		if options.ignore_unbreakable
+				&& is_unbreakable (content, config.line_length, & tab_stops) {
+			return None;
+		}
+
+		Some (format! (
+			"line too long ({} columns, maximum {})",
+			content_width,
+			config.line_length).into ())
+
+	}
+
+	fn column (
+		& self,
+		config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+
+		// the first character which goes past the maximum, in the first part
+		// which is too long
+
+		let tab_stops = config.tab_stops ();
+
+		Lines::new (& line.content)
+			.map (|part| lines::split_line_ending (part).0)
+			.find_map (|part| {
+				let mut width = 0;
+				part.chars ().position (|character| {
+					width = if character == '\t' {
+						width::next_tab_stop (width, & tab_stops)
+					} else {
+						width + width::char_width (character)
+					};
+					width > config.line_length
+				})
+			})
+			.map (|index| index + 1)
+
+	}
+
+	fn fix (& mut self, _config: & Config, _line: & mut Line) {}
+
+}
+
+/// A rule defined in the configuration file, which reports lines matching a
+/// regex and optionally fixes them with a replacement.
+#[ derive (Clone, Debug) ]
+pub struct RegexRule {
+	id: String,
+	regex: Regex,
+	replacement: Option <String>,
+	message: String,
+	filetypes: Vec <String>,
+}
+
+impl RegexRule {
+
+	pub fn new (
+		id: & str,
+		pattern: & str,
+		replacement: Option <String>,
+		message: String,
+		filetypes: Vec <String>,
+	) -> Result <RegexRule, String> {
+
+		let regex = match Regex::new (pattern) {
+			Ok (regex) => regex,
+			Err (error) => return Err (format! ("Invalid pattern for rule {}: {}", id, error)),
+		};
+
+		Ok (RegexRule {
+			id: id.to_owned (),
+			regex,
+			replacement,
+			message,
+			filetypes,
+		})
+
+	}
+
+}
+
+impl Rule for RegexRule {
+
+	fn id (& self) -> & str { & self.id }
+	fn description (& self) -> & str { & self.message }
+	fn fixable (& self, _config: & Config) -> bool { self.replacement.is_some () }
+
+	fn unfixable_reason (
+		& self,
+		_config: & Config,
+	) -> & str {
+		"the rule has no replacement"
+	}
+
+	fn check (
+		& mut self,
+		config: & Config,
+		line: & Line,
+	) -> Option <Cow <'static, str>> {
+
+		if ! self.filetypes.is_empty ()
+				&& ! self.filetypes.iter ().any (|filetype|
+					config.filetype.as_ref () == Some (filetype)) {
+			return None;
+		}
+
+		if ! self.regex.is_match (& line.content) {
+			return None;
+		}
+
+		Some (self.message.clone ().into ())
+
+	}
+
+	fn column (
+		& self,
+		_config: & Config,
+		line: & Line,
+	) -> Option <usize> {
+
+		let start = self.regex.find (& line.content) ?.start ();
+
+		Some (line.content [ .. start].chars ().count () + 1)
+
+	}
+
+	fn fix (
+		& mut self,
+		_config: & Config,
+		line: & mut Line,
+	) {
+
+		if let Some (ref replacement) = self.replacement {
+			line.content = Cow::Owned (
+				self.regex.replace_all (& line.content, replacement.as_str ()).into_owned ());
+		}
+
+	}
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use crate::diff::LineRanges;
+
+	use super::*;
+
+	/// Run the built in rules, returning the output along with the line,
+	/// column and rule of each finding.
+	fn run (
+		config: & Config,
+		contents: & str,
+	) -> (String, Vec <(u64, Option <usize>, String)>) {
+
+		let (output, findings) = Registry::builtin ().run (config, contents);
+
+		(output, findings.into_iter ()
+			.map (|finding| (finding.line_number, finding.column, finding.rule))
+			.collect ())
+
+	}
+
+	fn finding (
+		line_number: u64,
+		column: Option <usize>,
+		rule: & str,
+	) -> (u64, Option <usize>, String) {
+		(line_number, column, rule.to_owned ())
+	}
+
+	#[ test ]
+	fn trailing_whitespace () {
+
+		assert_eq! (
+			run (& Config::default (), "a  \nb \nc\n"),
+			("a\nb\nc\n".to_owned (), vec! [
+				finding (1, Some (2), "trailing-whitespace"),
+				finding (2, Some (2), "trailing-whitespace"),
+			]));
+
+	}
+
+	#[ test ]
+	fn columns_are_in_the_original_line () {
+
+		let config = Config { expand_tabs: true, ..Config::default () };
+
+		assert_eq! (
+			run (& config, "\tfoo  \n"),
+			("    foo\n".to_owned (), vec! [
+				finding (1, None, "tab-expansion"),
+				finding (1, Some (5), "trailing-whitespace"),
+			]));
+
+		let config = Config { unicode_whitespace: UnicodeWhitespace::Remove, ..config };
+
+		assert_eq! (
+			run (& config, "\ta\u{a0}\tb \n"),
+			("    a  b\n".to_owned (), vec! [
+				finding (1, None, "tab-expansion"),
+				finding (1, Some (3), "unicode-whitespace"),
+				finding (1, Some (6), "trailing-whitespace"),
+			]));
+
+	}
+
+	#[ test ]
+	fn aligns_characters () {
+
+		let chars = |value: & str| value.chars ().collect::<Vec <char>> ();
+
+		let align = |before, after| align_characters (& chars (before), & chars (after));
+
+		assert_eq! (align ("\tab", "    ab"), vec! [ 0, 0, 0, 0, 1, 2 ]);
+		assert_eq! (align ("a\tb\tc", "a   b   c"), vec! [ 0, 1, 1, 1, 2, 3, 3, 3, 4 ]);
+		assert_eq! (align ("a\u{0}b", "ab"), vec! [ 0, 2 ]);
+		assert_eq! (align ("ab  ", "ab"), vec! [ 0, 1 ]);
+
+		assert_eq! (original_index (Some (& [ 0, 2 ]), 3, 1), 2);
+		assert_eq! (original_index (Some (& [ 0, 2 ]), 3, 2), 3);
+		assert_eq! (original_index (None, 3, 5), 5);
+
+	}
+
+	#[ test ]
+	fn blank_lines () {
+
+		let config = Config { max_blank_lines: Some (1), ..Config::default () };
+
+		assert_eq! (
+			run (& config, "a\n\n \n\nb\n\n"),
+			("a\n\nb\n\n".to_owned (), vec! [
+				finding (3, None, "blank-lines"),
+				finding (4, None, "blank-lines"),
+			]));
+
+		assert_eq! (run (& Config::default (), "a\n\n\n\nb\n").1, Vec::new ());
+
+	}
+
+	#[ test ]
+	fn line_endings () {
+
+		assert_eq! (
+			run (& Config::default (), "a\r\nb\n"),
+			("a\nb\n".to_owned (), vec! [
+				finding (1, None, "mixed-line-endings"),
+				finding (1, None, "line-ending"),
+			]));
+
+		let config = Config { line_ending: LineEnding::Keep, ..Config::default () };
+
+		assert_eq! (run (& config, "a\r\nb\r\nc\n").0, "a\r\nb\r\nc\r\n");
+		assert_eq! (run (& config, "a\rb\r").1, Vec::new ());
+
+	}
+
+	#[ test ]
+	fn final_newline () {
+
+		let config = Config {
+			final_newline: true,
+			line_ending: LineEnding::Keep,
+			..Config::default ()
+		};
+
+		assert_eq! (
+			run (& config, "a\r\nb"),
+			("a\r\nb\r\n".to_owned (), vec! [ finding (2, None, "final-newline") ]));
+
+		assert_eq! (run (& config, "").1, Vec::new ());
+		assert_eq! (run (& Config::default (), "a").1, Vec::new ());
+
+	}
+
+	#[ test ]
+	fn control_characters () {
+
+		let (output, findings) = Registry::builtin ().run (& Config::default (), "a\u{0}b\u{8}\n");
+
+		assert_eq! (output, "a\u{0}b\u{8}\n");
+		assert_eq! (findings.len (), 1);
+		assert_eq! (findings [0].column, Some (2));
+		assert_eq! (findings [0].message, "2 control characters, first U+0000 at column 2");
+		assert! (! findings [0].fixable);
+		assert! (findings [0].reason.is_some ());
+
+		let config = Config { control_characters: ControlCharacters::Strip, ..Config::default () };
+		assert_eq! (run (& config, "a\u{0}b\u{8}c\n").0, "abc\n");
+
+		let config = Config { control_characters: ControlCharacters::Replace, ..Config::default () };
+		assert_eq! (run (& config, "a\u{0}b\u{8}c\n").0, "a b c\n");
+
+	}
+
+	#[ test ]
+	fn unicode_whitespace () {
+
+		let config = Config { unicode_whitespace: UnicodeWhitespace::Replace, ..Config::default () };
+
+		assert_eq! (
+			run (& config, "a\u{a0}b\u{200b}c\n"),
+			("a bc\n".to_owned (), vec! [ finding (1, Some (2), "unicode-whitespace") ]));
+
+	}
+
+	#[ test ]
+	fn mixed_indentation () {
+
+		assert_eq! (
+			run (& Config::default (), "  \tb\n"),
+			("\tb\n".to_owned (), vec! [ finding (1, None, "mixed-indentation") ]));
+
+		let config = Config { expand_tabs: true, ..Config::default () };
+
+		assert_eq! (run (& config, "  \tb\n").0, "    b\n");
+
+	}
+
+	#[ test ]
+	fn tabs_after_characters () {
+
+		let (output, findings) = Registry::builtin ().run (& Config::default (), "\ta\tb\n");
+
+		assert_eq! (output, "\ta\tb\n");
+		assert_eq! (findings.len (), 1);
+		assert_eq! (findings [0].rule, "tabs-after-characters");
+		assert_eq! (findings [0].column, Some (3));
+		assert! (findings [0].reason.is_some ());
+
+	}
+
+	#[ test ]
+	fn smart_tabs () {
+
+		let config = Config { smart_tabs: true, ..Config::default () };
+
+		assert_eq! (
+			run (& config, "      a\tb\n"),
+			("\t  a b\n".to_owned (), vec! [
+				finding (1, None, "smart-tabs-indent"),
+				finding (1, None, "smart-tabs-align"),
+			]));
+
+	}
+
+	#[ test ]
+	fn indent_size () {
+
+		let config = Config {
+			normalise_indent: true,
+			expand_tabs: true,
+			tab_size: 8,
+			indent_size: Some (4),
+			..Config::default ()
+		};
+
+		assert_eq! (
+			run (& config, "     a\n   b\n    c\n"),
+			("    a\n    b\n    c\n".to_owned (), vec! [
+				finding (1, Some (6), "indent-size"),
+				finding (2, Some (4), "indent-size"),
+			]));
+
+		let config = Config { indent_size: None, ..config };
+
+		assert_eq! (run (& config, "     a\n").0, "        a\n");
+
+		let config = Config { normalise_indent: false, ..config };
+
+		assert_eq! (run (& config, "     a\n").1, Vec::new ());
+
+	}
+
+	#[ test ]
+	fn markdown_line_breaks () {
+
+		let config = Config { filetype: Some ("markdown".to_owned ()), ..Config::default () };
+
+		assert_eq! (run (& config, "a  \nb   \n").0, "a  \nb\n");
+
+		let config = Config { markdown_line_breaks: MarkdownLineBreaks::Backslash, ..config };
+
+		assert_eq! (
+			run (& config, "a  \nb\n"),
+			("a\\\nb\n".to_owned (), vec! [ finding (1, None, "markdown-line-breaks") ]));
+
+		let config = Config { markdown_line_breaks: MarkdownLineBreaks::Remove, ..config };
+
+		assert_eq! (run (& config, "a  \n").0, "a\n");
+
+	}
+
+	#[ test ]
+	fn line_length () {
+
+		let config = Config { line_length: 10, ..Config::default () };
+
+		assert_eq! (
+			run (& config, "0123456789\n0123456789ab\n\t\tabc\n"),
+			("0123456789\n0123456789ab\n\t\tabc\n".to_owned (), vec! [
+				finding (2, Some (11), "line-length"),
+				finding (3, Some (5), "line-length"),
+			]));
+
+		let mut config = config;
+		config.line_length_options.ignore_urls = true;
+		config.line_length_options.ignore_unbreakable = true;
+
+		assert_eq! (run (& config, "see https://example.com/\nxxxxxxxxxxxxxxxx\n").1, Vec::new ());
+
+	}
+
+	#[ test ]
+	fn line_wrap () {
+
+		let mut config = Config {
+			line_length: 12,
+			filetype: Some ("markdown".to_owned ()),
+			..Config::default ()
+		};
+		config.line_length_options.wrap = true;
+
+		assert_eq! (
+			run (& config, "- one two three four\n```\none two three four\n```\n"),
+			("- one two\n  three four\n```\none two three four\n```\n".to_owned (), vec! [
+				finding (1, None, "line-wrap"),
+				finding (3, Some (13), "line-length"),
+			]));
+
+		assert_eq! (run (& config, "> one two three four").0, "> one two\n> three four");
+		assert_eq! (run (& config, "# one two three four\n").0, "# one two three four\n");
+
+	}
+
+	#[ test ]
+	fn line_wrap_only_for_prose () {
+
+		let mut config = Config {
+			line_length: 12,
+			filetype: Some ("python".to_owned ()),
+			..Config::default ()
+		};
+		config.line_length_options.wrap = true;
+
+		assert_eq! (
+			run (& config, "x = 'one two three'\n"),
+			("x = 'one two three'\n".to_owned (), vec! [ finding (1, Some (13), "line-length") ]));
+
+		config.line_length_options.wrap_filetypes.push ("python".to_owned ());
+
+		assert_eq! (run (& config, "x = 'one two three'\n").0, "x = 'one two\nthree'\n");
+
+	}
+
+	#[ test ]
+	fn ignored_lines () {
+
+		let config = Config {
+			ignore_line_pattern: vec! [ Regex::new ("keep").unwrap () ],
+			..Config::default ()
+		};
+
+		assert_eq! (run (& config, "keep \nfix \n"), ("keep \nfix\n".to_owned (), vec! [
+			finding (2, Some (4), "trailing-whitespace"),
+		]));
+
+		let mut line_ranges = LineRanges::new ();
+		line_ranges.add (2, 1);
+
+		let config = Config { line_ranges: Some (line_ranges), ..Config::default () };
+
+		assert_eq! (run (& config, "a \nb \nc \n"), ("a \nb\nc \n".to_owned (), vec! [
+			finding (2, Some (2), "trailing-whitespace"),
+		]));
+
+	}
+
+	#[ test ]
+	fn disabled_rules () {
+
+		let config = Config {
+			disable: vec! [ "trailing-whitespace".to_owned () ],
+			..Config::default ()
+		};
+
+		assert_eq! (run (& config, "a \n"), ("a \n".to_owned (), Vec::new ()));
+
+	}
+
+	#[ test ]
+	fn regex_rules () {
+
+		let mut registry = Registry::new ();
+
+		registry.add (RegexRule::new (
+			"no-todo",
+			"TODO",
+			Some ("DONE".to_owned ()),
+			"found todo".to_owned (),
+			vec! [ "rust".to_owned () ]).unwrap ());
+
+		registry.add_builtin ();
+
+		let config = Config { filetype: Some ("rust".to_owned ()), ..Config::default () };
+
+		let (output, findings) = registry.run (& config, "// é TODO \n");
+
+		assert_eq! (output, "// é DONE\n");
+		assert_eq! (findings [0].rule, "no-todo");
+		assert_eq! (findings [0].column, Some (6));
+		assert_eq! (findings [0].message, "found todo");
+		assert_eq! (findings [1].column, Some (10));
+
+		let (output, _) = registry.run (& Config::default (), "TODO\n");
+
+		assert_eq! (output, "TODO\n");
+
+		assert! (RegexRule::new ("bad", "(", None, String::new (), Vec::new ()).is_err ());
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
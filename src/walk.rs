@@ -0,0 +1,107 @@
+//! Finding files to process under a directory.
+//!
+//! Hidden files and directories, whose names start with a dot, are skipped,
+//! which also leaves out `.git`. Symlinked directories are only followed when
+//! asked, and each directory is only visited once, so links which point back
+//! up the tree don't loop forever.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::error::Error;
+
+/// The files under a directory, in order of their paths.
+pub fn files (
+	directory: & str,
+	follow_symlinks: bool,
+) -> Result <Vec <String>, Error> {
+
+	let mut walker = Walker {
+		follow_symlinks,
+		visited: HashSet::new (),
+		files: Vec::new (),
+	};
+
+	walker.visit (Path::new (directory)) ?;
+
+	Ok (walker.files)
+
+}
+
+struct Walker {
+	follow_symlinks: bool,
+	visited: HashSet <PathBuf>,
+	files: Vec <String>,
+}
+
+impl Walker {
+
+	fn visit (
+		& mut self,
+		directory: & Path,
+	) -> Result <(), Error> {
+
+		let io_error = |action, error| Error::io (action, directory.display ().to_string (), error);
+
+		let real_path = fs::canonicalize (directory)
+			.map_err (|error| io_error ("resolving", error)) ?;
+
+		if ! self.visited.insert (real_path) {
+			return Ok (());
+		}
+
+		let mut entries = fs::read_dir (directory)
+			.and_then (|entries| entries.collect::<Result <Vec <_>, _>> ())
+			.map_err (|error| io_error ("reading directory", error)) ?;
+
+		entries.sort_by_key (|entry| entry.file_name ());
+
+		for entry in entries {
+
+			if entry.file_name ().to_string_lossy ().starts_with ('.') {
+				continue;
+			}
+
+			let path = entry.path ();
+
+			let file_type = entry.file_type ()
+				.map_err (|error| Error::io ("reading", path.display ().to_string (), error)) ?;
+
+			// these follow links, to find what they point to. symlinked files
+			// are always listed, as the caller decides whether to rewrite them
+
+			if path.is_dir () {
+				if ! file_type.is_symlink () || self.follow_symlinks {
+					self.visit (& path) ?;
+				}
+			} else if path.is_file () {
+				self.add_file (& path) ?;
+			}
+
+		}
+
+		Ok (())
+
+	}
+
+	fn add_file (
+		& mut self,
+		path: & Path,
+	) -> Result <(), Error> {
+
+		match path.to_str () {
+			Some (path) => self.files.push (path.to_owned ()),
+			None => return Err (Error::Usage (format! (
+				"File name is not valid UTF-8: {}",
+				path.display ()))),
+		}
+
+		Ok (())
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,292 @@
+//! Display width of text, measured in terminal columns.
+//!
+//! This follows the same rules as `wcwidth`: combining marks and other
+//! zero-width characters take no columns, characters with an east asian width
+//! of wide or fullwidth take two, and everything else takes one. Tabs advance
+//! to the next tab stop.
+
+use std::iter;
+
+const ZERO_WIDTH: & [(u32, u32)] = & [
+	(0x0300, 0x036f), (0x0483, 0x0489), (0x0591, 0x05bd), (0x05bf, 0x05bf),
+	(0x05c1, 0x05c2), (0x05c4, 0x05c5), (0x05c7, 0x05c7), (0x0610, 0x061a),
+	(0x064b, 0x065f), (0x0670, 0x0670), (0x06d6, 0x06dc), (0x06df, 0x06e4),
+	(0x06e7, 0x06e8), (0x06ea, 0x06ed), (0x0711, 0x0711), (0x0730, 0x074a),
+	(0x07a6, 0x07b0), (0x07eb, 0x07f3), (0x0816, 0x0819), (0x081b, 0x0823),
+	(0x0825, 0x0827), (0x0829, 0x082d), (0x0859, 0x085b), (0x08d3, 0x08e1),
+	(0x08e3, 0x0902), (0x093a, 0x093a), (0x093c, 0x093c), (0x0941, 0x0948),
+	(0x094d, 0x094d), (0x0951, 0x0957), (0x0962, 0x0963), (0x0981, 0x0981),
+	(0x09bc, 0x09bc), (0x09c1, 0x09c4), (0x09cd, 0x09cd), (0x09e2, 0x09e3),
+	(0x0a01, 0x0a02), (0x0a3c, 0x0a3c), (0x0a41, 0x0a51), (0x0a70, 0x0a71),
+	(0x0a75, 0x0a75), (0x0a81, 0x0a82), (0x0abc, 0x0abc), (0x0ac1, 0x0ac8),
+	(0x0acd, 0x0acd), (0x0ae2, 0x0ae3), (0x0b01, 0x0b01), (0x0b3c, 0x0b3c),
+	(0x0b3f, 0x0b3f), (0x0b41, 0x0b44), (0x0b4d, 0x0b4d), (0x0b56, 0x0b56),
+	(0x0b62, 0x0b63), (0x0b82, 0x0b82), (0x0bc0, 0x0bc0), (0x0bcd, 0x0bcd),
+	(0x0c00, 0x0c00), (0x0c3e, 0x0c40), (0x0c46, 0x0c56), (0x0c62, 0x0c63),
+	(0x0cbc, 0x0cbc), (0x0ccc, 0x0ccd), (0x0ce2, 0x0ce3), (0x0d00, 0x0d01),
+	(0x0d41, 0x0d44), (0x0d4d, 0x0d4d), (0x0d62, 0x0d63), (0x0dca, 0x0dca),
+	(0x0dd2, 0x0dd6), (0x0e31, 0x0e31), (0x0e34, 0x0e3a), (0x0e47, 0x0e4e),
+	(0x0eb1, 0x0eb1), (0x0eb4, 0x0ebc), (0x0ec8, 0x0ecd), (0x0f18, 0x0f19),
+	(0x0f35, 0x0f35), (0x0f37, 0x0f37), (0x0f39, 0x0f39), (0x0f71, 0x0f7e),
+	(0x0f80, 0x0f84), (0x0f86, 0x0f87), (0x0f8d, 0x0fbc), (0x0fc6, 0x0fc6),
+	(0x102d, 0x1030), (0x1032, 0x1037), (0x1039, 0x103a), (0x103d, 0x103e),
+	(0x1058, 0x1059), (0x105e, 0x1060), (0x1071, 0x1074), (0x1082, 0x1082),
+	(0x1085, 0x1086), (0x108d, 0x108d), (0x109d, 0x109d), (0x1160, 0x11ff),
+	(0x135d, 0x135f), (0x1712, 0x1714), (0x1732, 0x1734), (0x1752, 0x1753),
+	(0x1772, 0x1773), (0x17b4, 0x17b5), (0x17b7, 0x17bd), (0x17c6, 0x17c6),
+	(0x17c9, 0x17d3), (0x17dd, 0x17dd), (0x180b, 0x180d), (0x18a9, 0x18a9),
+	(0x1920, 0x1922), (0x1927, 0x1928), (0x1932, 0x1932), (0x1939, 0x193b),
+	(0x1a17, 0x1a18), (0x1ab0, 0x1aff), (0x1b00, 0x1b03), (0x1b34, 0x1b34),
+	(0x1b36, 0x1b3a), (0x1b3c, 0x1b3c), (0x1b42, 0x1b42), (0x1b6b, 0x1b73),
+	(0x1dc0, 0x1dff), (0x200b, 0x200f), (0x202a, 0x202e), (0x2060, 0x2064),
+	(0x20d0, 0x20f0), (0x302a, 0x302d), (0x3099, 0x309a), (0xfe00, 0xfe0f),
+	(0xfe20, 0xfe2f), (0xfeff, 0xfeff), (0x1d167, 0x1d169), (0x1d17b, 0x1d182),
+	(0xe0001, 0xe007f), (0xe0100, 0xe01ef),
+];
+
+const DOUBLE_WIDTH: & [(u32, u32)] = & [
+	(0x1100, 0x115f), (0x231a, 0x231b), (0x2329, 0x232a), (0x23e9, 0x23ec),
+	(0x23f0, 0x23f0), (0x23f3, 0x23f3), (0x25fd, 0x25fe), (0x2614, 0x2615),
+	(0x2648, 0x2653), (0x267f, 0x267f), (0x2693, 0x2693), (0x26a1, 0x26a1),
+	(0x26aa, 0x26ab), (0x26bd, 0x26be), (0x26c4, 0x26c5), (0x26ce, 0x26ce),
+	(0x26d4, 0x26d4), (0x26ea, 0x26ea), (0x26f2, 0x26f3), (0x26f5, 0x26f5),
+	(0x26fa, 0x26fa), (0x26fd, 0x26fd), (0x2705, 0x2705), (0x270a, 0x270b),
+	(0x2728, 0x2728), (0x274c, 0x274c), (0x274e, 0x274e), (0x2753, 0x2755),
+	(0x2757, 0x2757), (0x2795, 0x2797), (0x27b0, 0x27b0), (0x27bf, 0x27bf),
+	(0x2b1b, 0x2b1c), (0x2b50, 0x2b50), (0x2b55, 0x2b55), (0x2e80, 0x303e),
+	(0x3041, 0x33ff), (0x3400, 0x4dbf), (0x4e00, 0x9fff), (0xa000, 0xa4cf),
+	(0xa960, 0xa97f), (0xac00, 0xd7a3), (0xf900, 0xfaff), (0xfe10, 0xfe19),
+	(0xfe30, 0xfe6f), (0xff00, 0xff60), (0xffe0, 0xffe6), (0x16fe0, 0x16fe4),
+	(0x17000, 0x18aff), (0x1b000, 0x1b2ff), (0x1f004, 0x1f004), (0x1f0cf, 0x1f0cf),
+	(0x1f18e, 0x1f18e), (0x1f191, 0x1f19a), (0x1f200, 0x1f251), (0x1f300, 0x1f64f),
+	(0x1f680, 0x1f6ff), (0x1f900, 0x1f9ff), (0x1fa70, 0x1faff), (0x20000, 0x2fffd),
+	(0x30000, 0x3fffd),
+];
+
+fn in_table (
+	table: & [(u32, u32)],
+	character: char,
+) -> bool {
+
+	let code_point = character as u32;
+
+	table.binary_search_by (|& (first, last)|
+		if last < code_point {
+			std::cmp::Ordering::Less
+		} else if first > code_point {
+			std::cmp::Ordering::Greater
+		} else {
+			std::cmp::Ordering::Equal
+		}
+	).is_ok ()
+
+}
+
+/// Number of columns a single character occupies, not counting tabs.
+pub fn char_width (
+	character: char,
+) -> usize {
+
+	if character.is_control () || in_table (ZERO_WIDTH, character) {
+		0
+	} else if in_table (DOUBLE_WIDTH, character) {
+		2
+	} else {
+		1
+	}
+
+}
+
+/// Where tabs advance to. Usually every tab stop is the same distance
+/// apart, but like vim's `vartabstop` each one can be different, with the
+/// last distance repeating.
+#[ derive (Clone, Debug, PartialEq, Eq) ]
+pub struct TabStops {
+	distances: Vec <usize>,
+}
+
+impl TabStops {
+
+	pub fn uniform (
+		tab_size: usize,
+	) -> TabStops {
+		TabStops { distances: vec! [ tab_size ] }
+	}
+
+	/// Tab stops at these distances apart, or at the tab size if none are
+	/// given.
+	pub fn new (
+		distances: & [usize],
+		tab_size: usize,
+	) -> TabStops {
+
+		if distances.is_empty () {
+			return TabStops::uniform (tab_size);
+		}
+
+		TabStops { distances: distances.to_vec () }
+
+	}
+
+	/// Whether tabs don't advance at all.
+	pub fn is_zero (
+		& self,
+	) -> bool {
+		self.distances.iter ().all (|distance| * distance == 0)
+	}
+
+	/// Column reached by a tab starting at the given column.
+	pub fn next (
+		& self,
+		column: usize,
+	) -> usize {
+
+		let mut stop = 0;
+
+		for & distance in & self.distances {
+			if column < stop + distance {
+				return stop + distance;
+			}
+			stop += distance;
+		}
+
+		match self.distances.last () {
+			Some (& distance) if distance > 0 =>
+				column + distance - (column - stop) % distance,
+			_ => column,
+		}
+
+	}
+
+}
+
+/// Column reached by a tab starting at the given column.
+pub fn next_tab_stop (
+	column: usize,
+	tab_stops: & TabStops,
+) -> usize {
+	tab_stops.next (column)
+}
+
+/// Replace tabs with spaces, keeping everything at the same column. The
+/// column the text starts at is needed to find the tab stops.
+pub fn expand_tabs (
+	content: & str,
+	start_column: usize,
+	tab_stops: & TabStops,
+) -> String {
+
+	let mut expanded_content = String::with_capacity (content.len ());
+	let mut column = start_column;
+
+	for character in content.chars () {
+
+		if character == '\t' {
+
+			let next_column = next_tab_stop (column, tab_stops);
+
+			expanded_content.extend (iter::repeat_n (' ', next_column - column));
+			column = next_column;
+
+		} else {
+
+			expanded_content.push (character);
+			column += char_width (character);
+
+		}
+
+	}
+
+	expanded_content
+
+}
+
+/// Number of columns a line occupies, excluding its line ending.
+pub fn line_width (
+	content: & str,
+	tab_stops: & TabStops,
+) -> usize {
+
+	content.chars ().fold (0, |column, character|
+		if character == '\t' {
+			next_tab_stop (column, tab_stops)
+		} else {
+			column + char_width (character)
+		})
+
+}
+
+#[ cfg (test) ]
+mod tests {
+
+	use super::*;
+
+	#[ test ]
+	fn character_widths () {
+
+		assert_eq! (char_width ('a'), 1);
+		assert_eq! (char_width ('é'), 1);
+		assert_eq! (char_width ('\u{301}'), 0);
+		assert_eq! (char_width ('\u{200b}'), 0);
+		assert_eq! (char_width ('\u{7}'), 0);
+		assert_eq! (char_width ('中'), 2);
+		assert_eq! (char_width ('😀'), 2);
+
+	}
+
+	#[ test ]
+	fn uniform_tab_stops () {
+
+		let tab_stops = TabStops::uniform (4);
+
+		assert_eq! (tab_stops.next (0), 4);
+		assert_eq! (tab_stops.next (3), 4);
+		assert_eq! (tab_stops.next (4), 8);
+		assert_eq! (tab_stops.next (9), 12);
+
+		assert! (! tab_stops.is_zero ());
+		assert! (TabStops::uniform (0).is_zero ());
+		assert_eq! (TabStops::uniform (0).next (3), 3);
+
+	}
+
+	#[ test ]
+	fn variable_tab_stops () {
+
+		let tab_stops = TabStops::new (& [ 2, 6 ], 4);
+
+		assert_eq! (tab_stops.next (0), 2);
+		assert_eq! (tab_stops.next (2), 8);
+		assert_eq! (tab_stops.next (8), 14);
+		assert_eq! (tab_stops.next (13), 14);
+
+		assert_eq! (TabStops::new (& [], 4), TabStops::uniform (4));
+
+	}
+
+	#[ test ]
+	fn expands_tabs () {
+
+		let tab_stops = TabStops::uniform (4);
+
+		assert_eq! (expand_tabs ("a\tb\t\tc", 0, & tab_stops), "a   b       c");
+		assert_eq! (expand_tabs ("\tb", 2, & tab_stops), "  b");
+		assert_eq! (expand_tabs ("中\tb", 0, & tab_stops), "中  b");
+
+	}
+
+	#[ test ]
+	fn line_widths () {
+
+		let tab_stops = TabStops::uniform (8);
+
+		assert_eq! (line_width ("", & tab_stops), 0);
+		assert_eq! (line_width ("ab\tc", & tab_stops), 9);
+		assert_eq! (line_width ("中文", & tab_stops), 4);
+		assert_eq! (line_width ("e\u{301}", & tab_stops), 1);
+
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,151 @@
+//! Copying extended attributes, such as SELinux labels or macOS quarantine
+//! flags, to a file which replaces another.
+//!
+//! This is supported on Linux and macOS. Elsewhere nothing is copied.
+
+use std::fs::File;
+
+/// Copy every extended attribute which can be read from one file and set on
+/// another. As with the owner, failures are ignored, as some attributes can
+/// only be set by root, and some filesystems don't support them at all.
+#[ cfg (any (target_os = "linux", target_os = "android", target_os = "macos")) ]
+pub fn copy (
+	source: & File,
+	target: & File,
+) {
+
+	use std::ffi::CStr;
+	use std::os::unix::io::AsRawFd as _;
+
+	let source_fd = source.as_raw_fd ();
+	let target_fd = target.as_raw_fd ();
+
+	// names are separated by NULs, including after the last one
+
+	let names = match read_buffer (|buffer, size| unsafe {
+		sys::list (source_fd, buffer.cast (), size)
+	}) {
+		Some (names) => names,
+		None => return,
+	};
+
+	for name in names.split_inclusive (|& byte| byte == 0) {
+
+		let name = match CStr::from_bytes_with_nul (name) {
+			Ok (name) => name,
+			Err (_) => continue,
+		};
+
+		let value = match read_buffer (|buffer, size| unsafe {
+			sys::get (source_fd, name.as_ptr (), buffer, size)
+		}) {
+			Some (value) => value,
+			None => continue,
+		};
+
+		unsafe {
+			sys::set (target_fd, name.as_ptr (), value.as_ptr ().cast (), value.len ());
+		}
+
+	}
+
+}
+
+#[ cfg (not (any (target_os = "linux", target_os = "android", target_os = "macos"))) ]
+pub fn copy (
+	_source: & File,
+	_target: & File,
+) {
+}
+
+/// Read a list or value whose size isn't known, by asking for the size
+/// first. This gives up if it changes in between.
+#[ cfg (any (target_os = "linux", target_os = "android", target_os = "macos")) ]
+fn read_buffer (
+	read: impl Fn (* mut libc::c_void, usize) -> isize,
+) -> Option <Vec <u8>> {
+
+	let size = usize::try_from (read (std::ptr::null_mut (), 0)).ok () ?;
+
+	let mut buffer = vec! [0; size];
+	let read_size = usize::try_from (read (buffer.as_mut_ptr ().cast (), size)).ok () ?;
+
+	if read_size != size {
+		return None;
+	}
+
+	Some (buffer)
+
+}
+
+#[ cfg (any (target_os = "linux", target_os = "android")) ]
+mod sys {
+
+	use libc::c_char;
+	use libc::c_int;
+	use libc::c_void;
+
+	pub unsafe fn list (
+		fd: c_int,
+		buffer: * mut c_char,
+		size: usize,
+	) -> isize {
+		unsafe { libc::flistxattr (fd, buffer, size) }
+	}
+
+	pub unsafe fn get (
+		fd: c_int,
+		name: * const c_char,
+		value: * mut c_void,
+		size: usize,
+	) -> isize {
+		unsafe { libc::fgetxattr (fd, name, value, size) }
+	}
+
+	pub unsafe fn set (
+		fd: c_int,
+		name: * const c_char,
+		value: * const c_void,
+		size: usize,
+	) -> c_int {
+		unsafe { libc::fsetxattr (fd, name, value, size, 0) }
+	}
+
+}
+
+#[ cfg (target_os = "macos") ]
+mod sys {
+
+	use libc::c_char;
+	use libc::c_int;
+	use libc::c_void;
+
+	pub unsafe fn list (
+		fd: c_int,
+		buffer: * mut c_char,
+		size: usize,
+	) -> isize {
+		unsafe { libc::flistxattr (fd, buffer, size, 0) }
+	}
+
+	pub unsafe fn get (
+		fd: c_int,
+		name: * const c_char,
+		value: * mut c_void,
+		size: usize,
+	) -> isize {
+		unsafe { libc::fgetxattr (fd, name, value, size, 0, 0) }
+	}
+
+	pub unsafe fn set (
+		fd: c_int,
+		name: * const c_char,
+		value: * const c_void,
+		size: usize,
+	) -> c_int {
+		unsafe { libc::fsetxattr (fd, name, value, size, 0, 0) }
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,111 @@
+//! Only fixing changed lines, with files named in different ways.
+
+mod common;
+
+use common::TempDir;
+
+/// A repository where the first line of `f.txt` had trailing whitespace
+/// before the last commit, and the second has gained some since.
+fn repository (
+	name: & str,
+) -> TempDir {
+
+	let dir = TempDir::repository (& format! ("changed-lines-{}", name));
+
+	dir.write ("sub/.keep", "");
+	dir.write ("f.txt", "a \nb\n");
+	dir.git (& ["add", "."]);
+	dir.git (& ["commit", "-q", "-m", "Add f.txt"]);
+	dir.write ("f.txt", "a \nc \n");
+
+	dir
+
+}
+
+/// Check the changed lines of a file, returning the exit status and output.
+fn check (
+	dir: & TempDir,
+	directory: & str,
+	filename: & str,
+) -> (i32, String) {
+	dir.run_in (directory, & ["check", "--changed-lines", filename])
+}
+
+#[ test ]
+fn current_directory_prefix () {
+
+	let dir = repository ("current-directory-prefix");
+
+	for filename in ["f.txt", "./f.txt", "."] {
+		let (status, output) = check (& dir, ".", filename);
+		assert_eq! (status, 1, "{}: {}", filename, output);
+		assert! (output.contains ("f.txt:2:2: "), "{}: {}", filename, output);
+		assert! (! output.contains ("f.txt:1:"), "{}: {}", filename, output);
+	}
+
+}
+
+#[ test ]
+fn subdirectory () {
+
+	let dir = repository ("subdirectory");
+
+	let (status, output) = check (& dir, "sub", "../f.txt");
+	assert_eq! (status, 1, "{}", output);
+	assert! (output.contains ("../f.txt:2:2: "), "{}", output);
+	assert! (! output.contains ("f.txt:1:"), "{}", output);
+
+}
+
+#[ test ]
+fn added_lines_which_look_like_headers () {
+
+	let dir = repository ("added-lines-like-headers");
+
+	dir.write ("f.txt", "++ foo \na \nc \n");
+
+	let (status, output) = check (& dir, ".", "f.txt");
+	assert_eq! (status, 1, "{}", output);
+	assert! (output.contains ("f.txt:1:7: "), "{}", output);
+	assert! (output.contains ("f.txt:3:2: "), "{}", output);
+	assert! (! output.contains ("f.txt:2:"), "{}", output);
+
+}
+
+#[ test ]
+fn names_which_git_quotes () {
+
+	let dir = repository ("names-git-quotes");
+
+	for filename in ["sp ace.txt", "q\"uote.txt"] {
+		dir.write (filename, "a \nb\n");
+		dir.git (& ["add", filename]);
+	}
+
+	dir.git (& ["commit", "-q", "-m", "Add quoted names"]);
+
+	for filename in ["sp ace.txt", "q\"uote.txt"] {
+		dir.write (filename, "a \nc \n");
+		let (status, output) = check (& dir, ".", filename);
+		assert_eq! (status, 1, "{}: {}", filename, output);
+		assert! (output.contains (& format! ("{}:2:2: ", filename)), "{}: {}", filename, output);
+		assert! (! output.contains (& format! ("{}:1:", filename)), "{}: {}", filename, output);
+	}
+
+}
+
+#[ test ]
+fn untracked_files () {
+
+	let dir = repository ("untracked-files");
+
+	dir.write ("new.txt", "a \nb \n");
+
+	let (status, output) = check (& dir, "sub", "../new.txt");
+	assert_eq! (status, 1, "{}", output);
+	assert! (output.contains ("../new.txt:1:2: "), "{}", output);
+	assert! (output.contains ("../new.txt:2:2: "), "{}", output);
+
+}
+
+// ex: noet ts=4 filetype=rust
@@ -0,0 +1,92 @@
+//! Running the command line tool on files in a temporary directory.
+
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+
+#[ test ]
+fn command_line_severity_overrides_config_file () {
+
+	let dir = TempDir::new ("cli-severity");
+	dir.write (".fix-whitespace.toml", "[severity]\nline-length = \"error\"\n");
+	dir.write ("long.txt", format! ("{}\n", "x".repeat (100)));
+
+	let (status, output) = dir.run (& ["check", "long.txt"]);
+	assert_eq! (status, 1, "{}", output);
+
+	let (status, output) = dir.run (& ["check", "--severity", "line-length=warning", "long.txt"]);
+	assert_eq! (status, 0, "{}", output);
+	assert! (output.contains ("[line-length, unfixable]"), "{}", output);
+
+	let (status, output) = dir.run (& ["check", "--severity", "line-length=off", "long.txt"]);
+	assert_eq! (status, 0, "{}", output);
+	assert! (! output.contains ("line-length"), "{}", output);
+
+}
+
+#[ test ]
+fn config_file_section_severity_overrides_command_line () {
+
+	let dir = TempDir::new ("cli-section-severity");
+	dir.write (".fix-whitespace.toml", "[glob.\"*.txt\"]\nseverity.line-length = \"off\"\n");
+	dir.write ("long.txt", format! ("{}\n", "x".repeat (100)));
+
+	let (status, output) = dir.run (& ["check", "--severity", "line-length=error", "long.txt"]);
+	assert_eq! (status, 0, "{}", output);
+
+}
+
+#[ test ]
+fn ignore_line_patterns_add_up () {
+
+	let dir = TempDir::new ("cli-ignore-line-pattern");
+	dir.write (".fix-whitespace.toml", "ignore-line-pattern = [ \"^keep\" ]\n");
+	dir.write ("lines.txt", "keep \nalso \nfix \n");
+
+	let (status, output) = dir.run (& ["check", "--ignore-line-pattern", "^also", "lines.txt"]);
+	assert_eq! (status, 1, "{}", output);
+	assert! (! output.contains ("lines.txt:1:"), "{}", output);
+	assert! (! output.contains ("lines.txt:2:"), "{}", output);
+	assert! (output.contains ("lines.txt:3:"), "{}", output);
+
+}
+
+#[ test ]
+fn binary_files_found_in_directories_are_skipped () {
+
+	let dir = TempDir::new ("cli-binary");
+	dir.write ("image.png", b"\x89PNG\r\n\x1a\n\0\xff");
+	dir.write ("nul.dat", "a\0 \n");
+	dir.write ("text.txt", "a \n");
+
+	let (status, output) = dir.run (& ["--yes"]);
+	assert_eq! (status, 0, "{}", output);
+	assert_eq! (dir.read ("text.txt"), "a\n");
+	assert_eq! (dir.read ("nul.dat"), "a\0 \n");
+
+	let (status, output) = dir.run (& ["image.png"]);
+	assert_eq! (status, 3, "{}", output);
+
+}
+
+#[ test ]
+fn files_which_would_be_refused_dont_need_confirming () {
+
+	let dir = TempDir::new ("cli-refused");
+	dir.write ("read-only.txt", "a \n");
+	dir.write ("text.txt", "a \n");
+
+	let path = dir.path.join ("read-only.txt");
+	let mut permissions = fs::metadata (& path).unwrap ().permissions ();
+	permissions.set_readonly (true);
+	fs::set_permissions (& path, permissions).unwrap ();
+
+	let (_, output) = dir.run (& ["--confirm-limit", "1"]);
+	assert_eq! (dir.read ("text.txt"), "a\n", "{}", output);
+	assert_eq! (dir.read ("read-only.txt"), "a \n", "{}", output);
+
+}
+
+// ex: noet ts=4 filetype=rust
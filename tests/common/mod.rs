@@ -0,0 +1,123 @@
+//! Fixtures shared by the integration tests, which run the command line tool
+//! in temporary directories and git repositories.
+
+#![ allow (dead_code) ]
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::process::Command;
+
+/// An empty directory for a test, which is removed when it's dropped.
+pub struct TempDir {
+	pub path: PathBuf,
+}
+
+impl TempDir {
+
+	pub fn new (
+		name: & str,
+	) -> TempDir {
+
+		let path = env::temp_dir ()
+			.join (format! ("fix-whitespace-{}-{}", name, process::id ()));
+
+		let _ = fs::remove_dir_all (& path);
+		fs::create_dir_all (& path).unwrap ();
+
+		TempDir { path }
+
+	}
+
+	/// An empty git repository, with a user to make commits as.
+	pub fn repository (
+		name: & str,
+	) -> TempDir {
+
+		let dir = TempDir::new (name);
+		dir.git (& ["init", "-q"]);
+		dir.git (& ["config", "user.name", "Test"]);
+		dir.git (& ["config", "user.email", "test@example.com"]);
+
+		dir
+
+	}
+
+	pub fn write (
+		& self,
+		filename: & str,
+		contents: impl AsRef <[u8]>,
+	) {
+
+		let path = self.path.join (filename);
+
+		if let Some (parent) = path.parent () {
+			fs::create_dir_all (parent).unwrap ();
+		}
+
+		fs::write (path, contents).unwrap ();
+
+	}
+
+	pub fn read (
+		& self,
+		filename: & str,
+	) -> String {
+		fs::read_to_string (self.path.join (filename)).unwrap ()
+	}
+
+	pub fn git (
+		& self,
+		args: & [& str],
+	) {
+
+		let status = Command::new ("git")
+			.args (args)
+			.current_dir (& self.path)
+			.status ()
+			.unwrap ();
+
+		assert! (status.success (), "git {} failed", args.join (" "));
+
+	}
+
+	/// Run the tool in this directory, returning the exit status and stdout.
+	pub fn run (
+		& self,
+		args: & [& str],
+	) -> (i32, String) {
+		self.run_in (".", args)
+	}
+
+	/// Run the tool in a subdirectory, returning the exit status and stdout.
+	pub fn run_in (
+		& self,
+		directory: impl AsRef <Path>,
+		args: & [& str],
+	) -> (i32, String) {
+
+		let output = Command::new (env! ("CARGO_BIN_EXE_fix-whitespace"))
+			.args (args)
+			.args (["--color", "never"])
+			.current_dir (self.path.join (directory))
+			.env_remove ("RUST_LOG")
+			.output ()
+			.unwrap ();
+
+		(output.status.code ().unwrap (), String::from_utf8 (output.stdout).unwrap ())
+
+	}
+
+}
+
+impl Drop for TempDir {
+
+	fn drop (& mut self) {
+		let _ = fs::remove_dir_all (& self.path);
+	}
+
+}
+
+// ex: noet ts=4 filetype=rust